@@ -1,30 +1,21 @@
-use serde::{Deserialize, Serialize};
-use serde_json::json;
-use wasm_bindgen::prelude::*;
-use yew::services::fetch::{FetchService, FetchTask, Request, Response};
-use yew::{
-    format::{Json, Nothing},
-    prelude::*,
-    utils::*,
-};
+use yew::agent::{Dispatched, Dispatcher};
+use yew::prelude::*;
 use crate::api::*;
-
-#[derive(Deserialize, Debug, Clone)]
-pub struct NoteResponse {
-    pub code: i64,
-}
+use crate::notifications::*;
+use crate::offline::*;
 
 #[derive(Debug)]
 pub enum AddNoteMsg {
     NoteEdit(String),
     NoteKeyDown(KeyboardEvent),
     SubmitNote,
+    SendNote(AddNotePayload),
 
     TagEdit(String),
     TagKeyDown(KeyboardEvent),
     AddTag(String),
 
-    SubmitResponse(Result<Vec<NoteResponse>, anyhow::Error>),
+    SubmitResponse(ApiResult<i64>),
 }
 
 pub struct AddNote {
@@ -32,7 +23,11 @@ pub struct AddNote {
     tag: String,     // holds text in the tag input
     tags: Vec<String>,
     link: ComponentLink<Self>,
-    submit_task: Option<FetchTask>,
+    api: ApiClient,
+    submit_task: Option<ApiTask>,
+    pending: Option<AddNotePayload>, // kept for retrying a failed submit
+    notifier: Dispatcher<NotificationBus>,
+    offline: Dispatcher<Offline>,
 }
 
 impl Component for AddNote {
@@ -44,8 +39,12 @@ impl Component for AddNote {
             content: "".to_string(),
             tag: String::from(""),
             tags: [].to_vec(),
-            link: link,
+            link,
+            api: ApiClient::new(),
             submit_task: None,
+            pending: None,
+            notifier: NotificationBus::dispatcher(),
+            offline: Offline::dispatcher(),
         }
     }
     fn change(&mut self, _props: Self::Properties) -> bool {
@@ -53,7 +52,6 @@ impl Component for AddNote {
     }
 
     fn update(&mut self, msg: Self::Message) -> bool {
-        let server = host().unwrap();
         match msg {
             AddNoteMsg::NoteEdit(content) => {
                 log::info!("note edit {:?}", content);
@@ -91,35 +89,46 @@ impl Component for AddNote {
 
             AddNoteMsg::SubmitNote => {
                 log::info!("self.content {:?}", self.content);
-                let query = format!("http://{}/submit/note", server);
                 let payload = AddNotePayload {
                     note_content: self.content.clone(),
                     tags: self.tags.clone(),
                 };
-                let body = json!({"pnContent": self.content.clone(),
-                                       "pnTags": self.tags.clone()});
-                let request = Request::post(query)
-                    .header("Content-Type", "application/json")
-                    .body(Json(&body))
-                    .expect("Could not build request.");
-                log::info!("request is {:?}", request);
-
-                let callback = self.link.callback_once(
-                    |response: Response<Json<Result<Vec<NoteResponse>, anyhow::Error>>>| {
-                        let Json(data) = response.into_body();
-                        AddNoteMsg::SubmitResponse(data)
-                    },
-                );
+                self.link.send_message(AddNoteMsg::SendNote(payload));
                 self.content = String::from(""); // TODO - oninput callback still fires and we're left with a black note
                 self.tags = [].to_vec();
-                log::info!("request payload {:?}", payload);
-                let task = FetchService::fetch(request, callback).expect("failed to start request");
-                self.submit_task = Some(task);
                 true
             }
 
+            AddNoteMsg::SendNote(payload) => {
+                log::info!("request payload {:?}", payload);
+                let callback = self.link.callback(AddNoteMsg::SubmitResponse);
+                match self.api.submit_note(&payload, callback) {
+                    Ok(task) => self.submit_task = Some(task),
+                    Err(error) => {
+                        let payload = payload.clone();
+                        let retry = self.link.callback_once(move |_| AddNoteMsg::SendNote(payload));
+                        notify_error(&mut self.notifier, "Could not add note", &error, retry)
+                    }
+                }
+                self.pending = Some(payload);
+                false
+            }
+
             AddNoteMsg::SubmitResponse(data) => {
                 log::info!("submitted, response code received {:?}", data);
+                self.submit_task = None;
+                match (data, self.pending.take()) {
+                    (Ok(_), _) => self.notifier.send(Notification::success("Note added")),
+                    (Err(ApiError::Offline), Some(payload)) => {
+                        self.offline.send(OfflineInput::Queue(OutboxItem::Note(payload)));
+                        self.notifier.send(Notification::info("Server unreachable, note will be added once it is back"));
+                    }
+                    (Err(error), Some(payload)) => {
+                        let retry = self.link.callback_once(move |_| AddNoteMsg::SendNote(payload));
+                        notify_error(&mut self.notifier, "Could not add note", &error, retry)
+                    }
+                    (Err(error), None) => self.notifier.send(Notification::error(error.to_string())),
+                }
                 true
             }
 
@@ -159,11 +168,11 @@ impl Component for AddNote {
                             } else {
                                 vec![]
                             }) }
-                    onsubmit={ self.link.callback(move |e: FocusEvent| AddNoteMsg::SubmitNote) }>
+                    onsubmit={ self.link.callback(move |_: FocusEvent| AddNoteMsg::SubmitNote) }>
                 </textarea>
                 <p/>
                 <input type="submit" value="Add Item" class="add-note-submit shadow-sm p-3 mb-5 bg-white rounded" 
-                    onclick = { self.link.callback(move |e: MouseEvent| AddNoteMsg::SubmitNote) } />
+                    onclick = { self.link.callback(move |_: MouseEvent| AddNoteMsg::SubmitNote) } />
             </div>
         }
     }
@@ -1,54 +1,51 @@
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use yew::callback::Callback;
+use yew::format::{Json, Nothing};
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+use yew::utils::{document, origin};
 
-#[derive(Deserialize, Debug, Clone)]
-pub struct Entry {
-    pub time: String,
-    #[serde(rename(deserialize = "entryID"))]
-    pub entry_id: i32,
-    content: String,
-    pub date: String,
-}
+use crate::api_cache;
+use crate::offline;
+use crate::prefs;
 
 // gallery view
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Cache {
-    #[serde(rename(deserialize = "cvTime"))]
+    #[serde(rename = "cvTime")]
     pub time: String,
-    #[serde(rename(deserialize = "cvForeignID"))]
+    #[serde(rename = "cvForeignID")]
     pub entry_id: i32,
-    #[serde(rename(deserialize = "cvContent"))]
+    #[serde(rename = "cvContent")]
     pub content: Option<String>,
-    #[serde(rename(deserialize = "cvDate"))]
+    #[serde(rename = "cvDate")]
     pub date: String,
-    #[serde(rename(deserialize = "cvUrl"))]
+    #[serde(rename = "cvUrl")]
     pub url: Option<String>,
-    #[serde(rename(deserialize = "cvThumbnailFile"))]
+    #[serde(rename = "cvThumbnailFile")]
     pub thumbnail_file: Option<String>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
-pub struct Tag {
-    pub tag_name: String,
-}
-
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Timestamp {
-    #[serde(rename(deserialize = "dtDay"))]
+    #[serde(rename = "dtDay")]
     pub day: (i32, i32, i32),
-    #[serde(rename(deserialize = "dtTimeOfDay"))]
+    #[serde(rename = "dtTimeOfDay")]
     pub time_of_day: (i32, i32, i32),
-    #[serde(rename(deserialize = "dtUTC"))]
-    pub utc: i64
+    #[serde(rename = "dtUTC")]
+    pub utc: i64,
 }
 
 // add_note
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AddNotePayload {
-    #[serde(rename(serialize = "pnContent", deserialize = "pnContent"))]
+    #[serde(rename = "pnContent")]
     pub note_content: String,
-    #[serde(rename(serialize = "pnTags", deserialize = "pnTags"))]
+    #[serde(rename = "pnTags")]
     pub tags: Vec<String>,
 }
 
@@ -56,12 +53,622 @@ pub struct AddNotePayload {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CompletedPayload {
-    #[serde(rename(serialize = "pcEntryID", deserialize = "pcEntryID"))]
+    #[serde(rename = "pcEntryID")]
     pub entry_id: i32,
-    #[serde(rename(serialize = "pcState", deserialize = "pcState"))]
+    #[serde(rename = "pcState")]
     pub state: bool,
 }
 
-pub struct CompletedResponse {
-    pub code: i64,
+// search/hits
+
+/// Field a search matched, `shSource` in `shared/DB.hs`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HitSource {
+    Url,
+    Title,
+    Tag,
+    Body,
+    Ocr,
+}
+
+impl HitSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            HitSource::Url => "URL",
+            HitSource::Title => "Title",
+            HitSource::Tag => "Tag",
+            HitSource::Body => "Page text",
+            HitSource::Ocr => "OCR",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    #[serde(rename = "shEntryID")]
+    pub entry_id: i32,
+    #[serde(rename = "shSource")]
+    pub source: HitSource,
+    // text around the match, for body and OCR hits
+    #[serde(rename = "shSnippet")]
+    pub snippet: Option<String>,
+}
+
+// submit/tags
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TagsPayload {
+    #[serde(rename = "ptEntryID")]
+    pub entry_id: i32,
+    #[serde(rename = "ptTags")]
+    pub tags: Vec<String>,
+}
+
+// link/entry/tags
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EntryTag {
+    #[serde(rename = "etEntryID")]
+    pub entry_id: i32,
+    // entries without tags come back as null from the LEFT JOIN
+    #[serde(rename = "etTag")]
+    pub tag: Option<String>,
+}
+
+// config
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ServerConfig {
+    #[serde(rename = "showTagThresh")]
+    pub tag_threshold: i32,
+    pub port: i32,
+    #[serde(rename = "dbFilename")]
+    pub db_filename: String,
+}
+
+// all/cache query parameters
+
+/// `sort` parameter of `/all/cache`, matches `SortBy` in `shared/DB.hs`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    Time,
+    Url,
+    Title,
+}
+
+impl SortBy {
+    pub fn as_param(&self) -> &'static str {
+        match self {
+            SortBy::Time => "time",
+            SortBy::Url => "url",
+            SortBy::Title => "title",
+        }
+    }
+}
+
+/// `sortdir` parameter of `/all/cache`, matches `SortDir` in `shared/DB.hs`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortDir {
+    Fwd,
+    Rev,
+}
+
+impl SortDir {
+    pub fn as_param(&self) -> &'static str {
+        match self {
+            SortDir::Fwd => "fwd",
+            SortDir::Rev => "rev",
+        }
+    }
+}
+
+/// Filters for `/all/cache`. Each field maps onto one `QueryParam` of
+/// `AllCacheAPI`, so setting one filter leaves the others in place.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheQuery {
+    pub sort: Option<SortBy>,
+    pub sortdir: Option<SortDir>,
+    pub tags: Vec<String>,
+    pub limit: Option<i32>,
+    /// rows to skip, for paging when not sorted by date
+    pub offset: Option<i32>,
+    pub hidecompleted: Option<bool>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+impl CacheQuery {
+    pub fn with_limit(limit: i32) -> Self {
+        Self {
+            limit: Some(limit),
+            ..Self::default()
+        }
+    }
+
+    /// Encoded query string (without the leading `?`) in the form `allCacheH`
+    /// parses: repeated `tag` params and `YYYY-MM-DD` days.
+    pub fn to_query_string(&self) -> String {
+        let mut params: Vec<String> = Vec::new();
+        if let Some(sort) = self.sort {
+            params.push(format!("sort={}", sort.as_param()));
+        }
+        if let Some(sortdir) = self.sortdir {
+            params.push(format!("sortdir={}", sortdir.as_param()));
+        }
+        for tag in &self.tags {
+            params.push(format!("tag={}", urlencoding::encode(tag)));
+        }
+        if let Some(limit) = self.limit {
+            params.push(format!("limit={}", limit));
+        }
+        if let Some(offset) = self.offset {
+            params.push(format!("offset={}", offset));
+        }
+        if let Some(hidecompleted) = self.hidecompleted {
+            params.push(format!("hidecompleted={}", hidecompleted));
+        }
+        if let Some(start) = self.start_date {
+            params.push(format!("startDate={}", start.format("%Y-%m-%d")));
+        }
+        if let Some(end) = self.end_date {
+            params.push(format!("endDate={}", end.format("%Y-%m-%d")));
+        }
+        params.join("&")
+    }
+}
+
+// server location
+
+/// Turns user input like `localhost:3000/` or `https://example.org/memex/`
+/// into a base url requests can be appended to.
+pub fn normalize_base_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    if url.starts_with("http://") || url.starts_with("https://") {
+        url.to_string()
+    } else {
+        format!("http://{}", url)
+    }
+}
+
+fn meta_base_url() -> Option<String> {
+    document()
+        .query_selector("meta[name=openmemex-server]")
+        .ok()??
+        .get_attribute("content")
+        .filter(|url| !url.trim().is_empty())
+}
+
+/// Base url of the OpenMemex server. Resolved in order from the localStorage
+/// override (set on the settings page), a `<meta name="openmemex-server">`
+/// tag in index.html, and finally the origin the page was served from.
+pub fn server_base_url() -> String {
+    prefs::get(prefs::SERVER_URL)
+        .filter(|url| !url.trim().is_empty())
+        .or_else(meta_base_url)
+        .or_else(|| origin().ok())
+        .map(|url| normalize_base_url(&url))
+        .unwrap_or_default()
+}
+
+/// Store (or with `None`, clear) the localStorage server override.
+pub fn set_server_base_url(url: Option<&str>) {
+    match url {
+        Some(url) => prefs::set(prefs::SERVER_URL, &normalize_base_url(url)),
+        None => prefs::remove(prefs::SERVER_URL),
+    }
+}
+
+// client
+
+#[derive(Debug, Clone)]
+pub enum ApiError {
+    /// The request could not be built or handed to the browser.
+    Request(String),
+    /// The server answered with a non-2xx status code.
+    Status(u16),
+    /// The response body was missing or did not match the expected shape.
+    Decode(String),
+    /// The server could not be reached and nothing was saved for offline use.
+    Offline,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Request(msg) => write!(f, "could not start request: {}", msg),
+            ApiError::Status(code) => write!(f, "server returned status {}", code),
+            ApiError::Decode(msg) => write!(f, "could not read response: {}", msg),
+            ApiError::Offline => write!(f, "server unreachable"),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+/// Handle for a request started by `ApiClient`, to be held until the
+/// callback fires. Dropping it cancels the request. Responses served from the
+/// in-memory cache are delivered right away and hold no `FetchTask`.
+#[derive(Debug)]
+pub struct ApiTask {
+    _fetch: Option<FetchTask>,
+}
+
+// how long GET responses are reused before going back to the server
+const TTL_ENTRIES: i64 = 60;
+const TTL_TAGS: i64 = 300;
+const TTL_TIMESTAMPS: i64 = 300;
+const TTL_COMPLETED: i64 = 30;
+
+fn parse<T: DeserializeOwned>(body: &str) -> ApiResult<T> {
+    serde_json::from_str(body).map_err(|e| ApiError::Decode(e.to_string()))
+}
+
+/// Typed wrapper around the Servant routes in `server/Main.hs`. Each method
+/// starts a request and returns an `ApiTask`. GET responses are kept in a
+/// small LRU cache shared by all clients, writes invalidate the routes they
+/// affect. Entries, tags and timestamps are also saved to IndexedDB and
+/// served from there when the server is unreachable.
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    base: String,
+}
+
+impl Default for ApiClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ApiClient {
+    pub fn new() -> Self {
+        Self {
+            base: server_base_url(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+
+    /// Url of a file under the server's `frontend` directory, such as the
+    /// `thumbnails/..` paths stored in the cache table.
+    pub fn frontend_file_url(&self, file: &str) -> String {
+        if file.starts_with("http://") || file.starts_with("https://") {
+            file.to_string()
+        } else {
+            self.url(&format!("/frontend/{}", file.trim_start_matches('/')))
+        }
+    }
+
+    /// Forget cached responses for every path starting with `prefix`, e.g.
+    /// `/all/cache` after entries changed outside of this client.
+    pub fn invalidate(&self, prefix: &str) {
+        api_cache::invalidate(prefix);
+    }
+
+    /// Raw response body of a 2xx answer. Network failures, which the
+    /// browser reports to `FetchService` as status 408 without a body, become
+    /// `ApiError::Offline`.
+    fn fetch<IN, E>(
+        &self,
+        request: Result<Request<IN>, E>,
+        callback: Callback<ApiResult<String>>,
+    ) -> ApiResult<ApiTask>
+    where
+        IN: Into<yew::format::Text>,
+        E: fmt::Display,
+    {
+        let request = request.map_err(|e| ApiError::Request(e.to_string()))?;
+        log::info!("api request: {} {}", request.method(), request.uri());
+        let handler = Callback::from(move |response: Response<yew::format::Text>| {
+            let status = response.status();
+            let body = response.into_body();
+            let unreachable = status.as_u16() == 408 && body.is_err();
+            offline::report_online(!unreachable);
+            let result = if unreachable {
+                Err(ApiError::Offline)
+            } else if status.is_success() {
+                body.map_err(|e| ApiError::Decode(e.to_string()))
+            } else {
+                Err(ApiError::Status(status.as_u16()))
+            };
+            callback.emit(result);
+        });
+        let task =
+            FetchService::fetch(request, handler).map_err(|e| ApiError::Request(e.to_string()))?;
+        Ok(ApiTask { _fetch: Some(task) })
+    }
+
+    /// `persist` keeps the response in IndexedDB for offline use.
+    fn get<T>(
+        &self,
+        path: &str,
+        ttl: i64,
+        persist: bool,
+        callback: Callback<ApiResult<T>>,
+    ) -> ApiResult<ApiTask>
+    where
+        T: DeserializeOwned + 'static,
+    {
+        let key = api_cache::key(path);
+        if let Some(body) = api_cache::lookup(&key) {
+            log::info!("api cache hit: {}", key);
+            callback.emit(parse(&body));
+            return Ok(ApiTask { _fetch: None });
+        }
+        let handler = Callback::from(move |result: ApiResult<String>| match result {
+            Ok(body) => {
+                api_cache::store(key.clone(), body.clone(), ttl);
+                if persist {
+                    offline::save_response(&key, &body);
+                }
+                callback.emit(parse(&body));
+            }
+            Err(ApiError::Offline) if persist => {
+                log::info!("server unreachable, loading saved {}", key);
+                let callback = callback.clone();
+                offline::load_response(&key, move |saved| {
+                    callback.emit(saved.ok_or(ApiError::Offline).and_then(|body| parse(&body)))
+                });
+            }
+            Err(error) => callback.emit(Err(error)),
+        });
+        self.fetch(Request::get(self.url(path)).body(Nothing), handler)
+    }
+
+    /// POST `body`, then drop cached responses under each of `invalidates`.
+    fn post<B, T>(
+        &self,
+        path: &str,
+        body: &B,
+        invalidates: Vec<String>,
+        callback: Callback<ApiResult<T>>,
+    ) -> ApiResult<ApiTask>
+    where
+        B: Serialize,
+        T: DeserializeOwned + 'static,
+    {
+        let request = Request::post(self.url(path))
+            .header("Content-Type", "application/json")
+            .body(Json(body));
+        let handler = Callback::from(move |result: ApiResult<String>| {
+            if result.is_ok() {
+                for prefix in &invalidates {
+                    api_cache::invalidate(prefix);
+                }
+            }
+            callback.emit(result.and_then(|body| parse(&body)));
+        });
+        self.fetch(request, handler)
+    }
+
+    /// `GET /all/cache?{query}` - gallery entries.
+    pub fn all_cache(
+        &self,
+        query: &CacheQuery,
+        callback: Callback<ApiResult<Vec<Cache>>>,
+    ) -> ApiResult<ApiTask> {
+        let path = format!("/all/cache?{}", query.to_query_string());
+        self.get(&path, TTL_ENTRIES, true, callback)
+    }
+
+    /// `GET /all/tags?min={min}` - tags with at least `min` entries.
+    pub fn all_tags(
+        &self,
+        min: Option<i32>,
+        callback: Callback<ApiResult<Vec<String>>>,
+    ) -> ApiResult<ApiTask> {
+        let path = match min {
+            Some(min) => format!("/all/tags?min={}", min),
+            None => "/all/tags".to_string(),
+        };
+        self.get(&path, TTL_TAGS, true, callback)
+    }
+
+    /// `GET /all/timestamps` - one timestamp per entry, used by the timeline.
+    pub fn all_timestamps(
+        &self,
+        callback: Callback<ApiResult<Vec<Timestamp>>>,
+    ) -> ApiResult<ApiTask> {
+        self.get("/all/timestamps", TTL_TIMESTAMPS, true, callback)
+    }
+
+    /// `GET /search/{query}` - substring match over url, title and tags.
+    pub fn search(
+        &self,
+        query: &str,
+        callback: Callback<ApiResult<Vec<Cache>>>,
+    ) -> ApiResult<ApiTask> {
+        let path = format!("/search/{}", urlencoding::encode(query));
+        self.get(&path, TTL_ENTRIES, false, callback)
+    }
+
+    /// `GET /search/hits/{query}` - which field `search` matched for each
+    /// of its results.
+    pub fn search_hits(
+        &self,
+        query: &str,
+        callback: Callback<ApiResult<Vec<SearchHit>>>,
+    ) -> ApiResult<ApiTask> {
+        let path = format!("/search/hits/{}", urlencoding::encode(query));
+        self.get(&path, TTL_ENTRIES, false, callback)
+    }
+
+    /// `POST /submit/note` - returns the id of the new entry.
+    pub fn submit_note(
+        &self,
+        payload: &AddNotePayload,
+        callback: Callback<ApiResult<i64>>,
+    ) -> ApiResult<ApiTask> {
+        let invalidates = vec!["/all/".to_string(), "/search/".to_string(), "/link/".to_string()];
+        self.post("/submit/note", payload, invalidates, callback)
+    }
+
+    /// `POST /submit/completed` - set or clear the completed flag of an entry.
+    pub fn submit_completed(
+        &self,
+        payload: &CompletedPayload,
+        callback: Callback<ApiResult<i64>>,
+    ) -> ApiResult<ApiTask> {
+        // hidecompleted queries change too
+        let invalidates = vec![
+            format!("/get/completed/{}", payload.entry_id),
+            "/all/cache".to_string(),
+            "/all/completed".to_string(),
+        ];
+        self.post("/submit/completed", payload, invalidates, callback)
+    }
+
+    /// `POST /submit/tags` - add tags to an existing entry, returns how many
+    /// were new.
+    pub fn submit_tags(
+        &self,
+        payload: &TagsPayload,
+        callback: Callback<ApiResult<i64>>,
+    ) -> ApiResult<ApiTask> {
+        let invalidates = vec![
+            "/all/".to_string(),
+            "/search/".to_string(),
+            "/link/".to_string(),
+        ];
+        self.post("/submit/tags", payload, invalidates, callback)
+    }
+
+    /// `POST /submit/tags/remove` - remove tags from an entry, returns how
+    /// many were removed.
+    pub fn remove_tags(
+        &self,
+        payload: &TagsPayload,
+        callback: Callback<ApiResult<i64>>,
+    ) -> ApiResult<ApiTask> {
+        let invalidates = vec![
+            "/all/".to_string(),
+            "/search/".to_string(),
+            "/link/".to_string(),
+        ];
+        self.post("/submit/tags/remove", payload, invalidates, callback)
+    }
+
+    /// `GET /all/completed` - ids of the entries marked as completed.
+    pub fn all_completed(&self, callback: Callback<ApiResult<Vec<i32>>>) -> ApiResult<ApiTask> {
+        self.get("/all/completed", TTL_COMPLETED, true, callback)
+    }
+
+    /// `GET /get/cache/{entry_id}` - `None` when the entry has no cache row.
+    pub fn get_cache(
+        &self,
+        entry_id: i32,
+        callback: Callback<ApiResult<Option<Cache>>>,
+    ) -> ApiResult<ApiTask> {
+        let unwrap = callback.reform(|result: ApiResult<Vec<Cache>>| {
+            result.map(|entries| entries.into_iter().next())
+        });
+        self.get(&format!("/get/cache/{}", entry_id), TTL_ENTRIES, true, unwrap)
+    }
+
+    /// `GET /get/completed/{entry_id}` - the server wraps the flag in a
+    /// singleton list, this unwraps it.
+    pub fn get_completed(
+        &self,
+        entry_id: i32,
+        callback: Callback<ApiResult<bool>>,
+    ) -> ApiResult<ApiTask> {
+        let unwrap = callback.reform(|result: ApiResult<Vec<bool>>| {
+            result.and_then(|flags| {
+                flags
+                    .first()
+                    .copied()
+                    .ok_or_else(|| ApiError::Decode("empty completed response".to_string()))
+            })
+        });
+        self.get(&format!("/get/completed/{}", entry_id), TTL_COMPLETED, false, unwrap)
+    }
+
+    /// `GET /link/entry/tags?filter=..` - entry/tag pairs, optionally
+    /// restricted to the given tags.
+    pub fn link_entry_tags(
+        &self,
+        filter: &[String],
+        callback: Callback<ApiResult<Vec<EntryTag>>>,
+    ) -> ApiResult<ApiTask> {
+        let params = filter
+            .iter()
+            .map(|tag| format!("filter={}", urlencoding::encode(tag)))
+            .collect::<Vec<_>>()
+            .join("&");
+        self.get(&format!("/link/entry/tags?{}", params), TTL_TAGS, false, callback)
+    }
+
+    /// `GET /config` - the server's command line configuration.
+    pub fn config(&self, callback: Callback<ApiResult<Vec<ServerConfig>>>) -> ApiResult<ApiTask> {
+        self.get("/config", TTL_TAGS, false, callback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn empty_query() {
+        assert_eq!(CacheQuery::default().to_query_string(), "");
+    }
+
+    #[test]
+    fn params_in_api_order() {
+        let query = CacheQuery {
+            sort: Some(SortBy::Title),
+            sortdir: Some(SortDir::Rev),
+            tags: vec!["rust".to_string()],
+            limit: Some(50),
+            offset: Some(100),
+            hidecompleted: Some(true),
+            start_date: Some(date(2021, 6, 1)),
+            end_date: Some(date(2021, 6, 30)),
+        };
+        assert_eq!(
+            query.to_query_string(),
+            "sort=title&sortdir=rev&tag=rust&limit=50&offset=100&hidecompleted=true\
+             &startDate=2021-06-01&endDate=2021-06-30"
+        );
+    }
+
+    #[test]
+    fn repeated_and_escaped_tags() {
+        let query = CacheQuery {
+            tags: vec!["a".to_string(), "machine learning".to_string(), "c&d=e".to_string()],
+            ..CacheQuery::default()
+        };
+        assert_eq!(query.to_query_string(), "tag=a&tag=machine%20learning&tag=c%26d%3De");
+    }
+
+    #[test]
+    fn hidecompleted_both_ways() {
+        let mut query = CacheQuery::with_limit(10);
+        query.hidecompleted = Some(false);
+        assert_eq!(query.to_query_string(), "limit=10&hidecompleted=false");
+        query.hidecompleted = Some(true);
+        assert_eq!(query.to_query_string(), "limit=10&hidecompleted=true");
+    }
+
+    #[test]
+    fn dates_are_zero_padded() {
+        let query = CacheQuery {
+            start_date: Some(date(987, 1, 2)),
+            end_date: Some(date(2021, 12, 9)),
+            ..CacheQuery::default()
+        };
+        assert_eq!(query.to_query_string(), "startDate=0987-01-02&endDate=2021-12-09");
+    }
 }
@@ -0,0 +1,129 @@
+use std::cell::RefCell;
+
+// Response bodies of GET requests, most recently used first. Shared by every
+// ApiClient so remounted components (gallery <-> detail) don't refetch.
+
+const CAPACITY: usize = 32;
+
+struct CachedResponse {
+    key: String,
+    body: String,
+    expires_ms: i64,
+}
+
+thread_local! {
+    static CACHE: RefCell<Vec<CachedResponse>> = const { RefCell::new(Vec::new()) };
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Cache key for a request path: query params are sorted so that
+/// `?tag=a&limit=5` and `?limit=5&tag=a` share an entry.
+pub fn key(path: &str) -> String {
+    match path.split_once('?') {
+        Some((route, query)) => {
+            let mut params: Vec<&str> = query.split('&').filter(|p| !p.is_empty()).collect();
+            params.sort_unstable();
+            format!("{}?{}", route, params.join("&"))
+        }
+        None => path.to_string(),
+    }
+}
+
+pub fn lookup(key: &str) -> Option<String> {
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let index = cache.iter().position(|c| c.key == key)?;
+        let hit = cache.remove(index);
+        if hit.expires_ms < now_ms() {
+            return None;
+        }
+        let body = hit.body.clone();
+        cache.insert(0, hit);
+        Some(body)
+    })
+}
+
+pub fn store(key: String, body: String, ttl_secs: i64) {
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        cache.retain(|c| c.key != key);
+        cache.insert(
+            0,
+            CachedResponse {
+                key,
+                body,
+                expires_ms: now_ms() + ttl_secs * 1000,
+            },
+        );
+        cache.truncate(CAPACITY);
+    })
+}
+
+/// Drop every cached response whose path starts with `prefix`.
+pub fn invalidate(prefix: &str) {
+    CACHE.with(|cache| cache.borrow_mut().retain(|c| !c.key.starts_with(prefix)))
+}
+
+#[cfg(test)]
+mod tests {
+    // every test runs on its own thread, so with its own empty `CACHE`
+    use super::*;
+
+    fn fill(count: usize) {
+        for i in 0..count {
+            store(format!("/get/cache/{}", i), i.to_string(), 60);
+        }
+    }
+
+    #[test]
+    fn key_sorts_query_params() {
+        assert_eq!(key("/all/cache?tag=a&limit=5"), key("/all/cache?limit=5&tag=a"));
+        assert_eq!(key("/all/cache?tag=b&tag=a&"), "/all/cache?tag=a&tag=b");
+        assert_ne!(key("/all/cache?tag=a"), key("/all/cache?tag=b"));
+        assert_eq!(key("/all/tags"), "/all/tags");
+    }
+
+    #[test]
+    fn store_and_lookup() {
+        assert_eq!(lookup("/all/tags"), None);
+        store("/all/tags".to_string(), "[]".to_string(), 60);
+        assert_eq!(lookup("/all/tags"), Some("[]".to_string()));
+        store("/all/tags".to_string(), "[\"a\"]".to_string(), 60);
+        assert_eq!(lookup("/all/tags"), Some("[\"a\"]".to_string()));
+    }
+
+    #[test]
+    fn expired_entries_are_dropped() {
+        store("/all/tags".to_string(), "[]".to_string(), -1);
+        assert_eq!(lookup("/all/tags"), None);
+        // and not kept around either
+        CACHE.with(|cache| assert!(cache.borrow().is_empty()));
+    }
+
+    #[test]
+    fn capacity_evicts_least_recently_used() {
+        fill(CAPACITY);
+        // touching the oldest entry makes the second oldest the next to go
+        assert_eq!(lookup("/get/cache/0"), Some("0".to_string()));
+        store("/get/cache/new".to_string(), "new".to_string(), 60);
+        CACHE.with(|cache| assert_eq!(cache.borrow().len(), CAPACITY));
+        assert_eq!(lookup("/get/cache/1"), None);
+        assert_eq!(lookup("/get/cache/0"), Some("0".to_string()));
+        assert_eq!(lookup("/get/cache/2"), Some("2".to_string()));
+        assert_eq!(lookup("/get/cache/new"), Some("new".to_string()));
+    }
+
+    #[test]
+    fn invalidate_by_prefix() {
+        store("/all/cache?limit=5".to_string(), "a".to_string(), 60);
+        store("/all/tags".to_string(), "b".to_string(), 60);
+        store("/get/cache/1".to_string(), "c".to_string(), 60);
+        invalidate("/all/");
+        assert_eq!(lookup("/all/cache?limit=5"), None);
+        assert_eq!(lookup("/all/tags"), None);
+        assert_eq!(lookup("/get/cache/1"), Some("c".to_string()));
+    }
+}
@@ -1,67 +1,534 @@
 use crate::add_note::*;
 use crate::api::*;
+use crate::batch::*;
 use crate::app_router::*;
 use crate::cards::*;
 use crate::detail::*;
+use crate::facets::*;
+use crate::keymap::*;
+use crate::notifications::*;
+use crate::offline::*;
+use crate::palette::{self, Command, Palette};
+use crate::prefs;
 use crate::space::*;
 use crate::queue::*;
+use crate::saved_searches::*;
+use crate::search::*;
+use crate::search_box::*;
 use crate::settings::*;
+use crate::sort::*;
 use crate::timeline::*;
 use crate::tags::*;
-use std::collections::HashSet;
-use yew::services::fetch::{FetchService, FetchTask, Request, Response};
-use yew::{
-    format::{Json, Nothing},
-    prelude::*,
-    utils::host,
-};
-use urlencoding::encode;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlElement;
+use yew::agent::{Bridge, Bridged, Dispatched, Dispatcher};
+use yew::prelude::*;
+use yew::services::timeout::{TimeoutService, TimeoutTask};
+use yew::utils::document;
+use yew_router::agent::RouteRequest;
 use yew_router::prelude::*;
+use yew_router::service::RouteService;
 use chrono::*;
 
 pub type Link = RouterAnchor<AppRoute>;
 
+// entry id -> tags
+type EntryTags = HashMap<i32, HashSet<String>>;
+
+// pause in typing before the gallery follows the search box
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
 #[derive(Debug)]
 pub struct Config {
     tag_threshold: i32,
 }
 
-#[derive(Debug)]
 pub struct App {
-    cache_task: Option<FetchTask>,
-    tag_task: Option<FetchTask>,
+    api: ApiClient,
+    cache_task: Option<ApiTask>,
+    page_task: Option<ApiTask>,
+    tag_task: Option<ApiTask>,
+    // bumped for every entries request, responses for older ones are dropped
+    generation: u64,
+    // threshold the current `tags` were fetched with
+    tags_threshold: Option<i32>,
     entries: Option<Vec<Cache>>,
+    // false once a page came back short, i.e. there is nothing older to load
+    has_more: bool,
+    // larger limit for re-requesting the cursor day when a whole page fell
+    // on it, see `ReceivePage`
+    page_limit: Option<i32>,
+    // `/all/cache` rows received so far, where the next page starts when
+    // not sorted by date
+    page_offset: i32,
     selected_entry: Option<Cache>,
     tags: Option<Vec<String>>,
-    selected_tags: HashSet<String>,
+    selected_tags: TagSelection,
+    // tags of the loaded entries, for the selections `/all/cache` can't
+    // express; keyed by the tags they were fetched for
+    entry_tags: Option<(Vec<String>, EntryTags)>,
+    entry_tags_task: Option<ApiTask>,
+    // ids of completed entries, for badges and hiding search results
+    completed: HashSet<i32>,
+    completed_task: Option<ApiTask>,
     link: ComponentLink<Self>,
-    error: Option<String>,
-    default_query: String,
-    query: String,
+    notifier: Dispatcher<NotificationBus>,
+    offline: Dispatcher<Offline>,
+    // the gallery filters are mirrored in the address bar
+    router: RouteAgentDispatcher<()>,
+    _route_changes: RouteAgentBridge<()>,
+    _keys: Box<dyn Bridge<KeyBindings>>,
+    // card picked with the keyboard
+    cursor: Option<i32>,
+    show_help: bool,
+    show_palette: bool,
+    // cards picked for the batch actions, `select_anchor` starts Shift-click ranges
+    selecting: bool,
+    selected: HashSet<i32>,
+    select_anchor: Option<i32>,
+    // completed toggles and tags from the keyboard, in flight per entry
+    completed_tasks: HashMap<i32, ApiTask>,
+    tag_tasks: HashMap<i32, ApiTask>,
+    query: CacheQuery,
+    sort: GallerySort,
+    view_mode: ViewMode,
+    // thumbnail width of the grid view
+    grid_size: u32,
+    // date buckets of the gallery
+    group_by: GroupBy,
+    // when set, the gallery shows `/search` results narrowed by the filters
+    // in `query` and `selected_tags`
+    search: Option<SearchQuery>,
     search_query: String,
+    // shown under the search box until the text is edited
+    search_error: Option<SearchError>,
+    search_timeout: Option<TimeoutTask>,
+    // set by search-as-you-type: the url is replaced rather than pushed and
+    // an empty result isn't worth a toast
+    typing: bool,
+    // where `/search` matched, keyed by the term it was fetched for
+    hits: Option<(String, HashMap<i32, SearchHit>)>,
+    hits_task: Option<ApiTask>,
+    // submitted search texts, most recent first
+    history: Vec<String>,
     config: Config,
 }
 
 #[derive(Debug)]
 pub enum AppMsg {
     GetEntries,
-    ReceiveEntries(Result<Vec<Cache>, anyhow::Error>),
-    ReceiveTags(Result<Vec<String>, anyhow::Error>),
-    KeyDown,
+    ReceiveEntries(u64, ApiResult<Vec<Cache>>),
+    LoadMore,
+    RetryPage,
+    ReceivePage(u64, Option<NaiveDate>, ApiResult<Vec<Cache>>),
+    GetTags,
+    ReceiveTags(i32, ApiResult<Vec<String>>),
+    Key(Action),
+    ClosePalette,
+    Selecting(bool),
+    Select(i32, bool),
+    SelectAll,
+    Batch(BatchEvent),
+    Command(Command),
+    ToggleCompleted(i32),
+    SetCompleted(i32, bool),
+    CompletedToggled(CompletedPayload, ApiResult<i64>),
+    TagEntry(i32),
+    EntryTagged(TagsPayload, ApiResult<i64>),
     // callback events
     CardClick(Option<Cache>),
-    TagClick(Option<String>),
+    TagClick(TagSelection),
+    GetEntryTags,
+    GetCompleted,
+    ReceiveCompleted(ApiResult<Vec<i32>>),
+    HideCompleted(bool),
+    ReceiveEntryTags(Vec<String>, ApiResult<Vec<EntryTag>>),
     TimelineEvt(Option<(NaiveDateTime, NaiveDateTime)>),
-
-    //
-    SortByDate,
-    SortByUrl,
+    FacetRemoved(FacetChange),
+    SortChange(GallerySort),
+    ViewModeChange(ViewMode),
+    GridSize(u32),
+    GroupByChange(GroupBy),
     SearchEdit(String),
-    SearchKeyDown(KeyboardEvent),
     SearchSubmit,
+    RunSearch(String),
+    SearchTyped,
+    GetHits,
+    ReceiveHits(String, ApiResult<Vec<SearchHit>>),
+    RouteChanged(Route<()>),
+    ShowGallery,
+}
+
+/// Search text from the address bar, with its operators folded into
+/// `query` and `tags` like a submitted search. Unparsable text is dropped.
+fn search_from_url(text: Option<String>, query: &mut CacheQuery, tags: &mut TagSelection) -> Option<SearchQuery> {
+    let parsed = parse(&text?, Local::now().date_naive()).ok()?;
+    parsed.apply(query, tags)
 }
 
 impl App {
+    /// Query for the page after the loaded entries. In date order the oldest
+    /// loaded day (newest for `sortdir=fwd`) becomes the date cursor, the
+    /// server treats it as inclusive so the overlap is removed in
+    /// `ReceivePage`, which raises `page_limit` when the overlap filled the
+    /// whole page. Domain and title order skip the rows already received.
+    fn next_page(&self) -> Option<(Option<NaiveDate>, CacheQuery)> {
+        let mut query = self.query.clone();
+        if self.page_limit.is_some() {
+            query.limit = self.page_limit;
+        }
+        if self.sort.key != SortKey::Date {
+            query.offset = Some(self.page_offset);
+            return Some((None, query));
+        }
+        let days = self
+            .entries
+            .as_ref()?
+            .iter()
+            .filter_map(|e| NaiveDate::parse_from_str(&e.date, "%Y-%m-%d").ok());
+        let cursor = if query.sortdir == Some(SortDir::Fwd) {
+            let cursor = days.max()?;
+            query.start_date = Some(cursor);
+            cursor
+        } else {
+            let cursor = days.min()?;
+            query.end_date = Some(cursor);
+            cursor
+        };
+        Some((Some(cursor), query))
+    }
+
+    /// Text for `/search`. Queries with only client-side operators page
+    /// through `/all/cache` instead.
+    fn search_term(&self) -> Option<&str> {
+        self.search.as_ref()?.server_term()
+    }
+
+    /// Whether `selected_tags` are applied here rather than by the server.
+    /// Search results ignore tags, so there it's every selection.
+    fn client_tag_filter(&self) -> bool {
+        match self.search_term() {
+            Some(_) => !self.selected_tags.is_empty(),
+            None => self.selected_tags.needs_client_filter(),
+        }
+    }
+
+    /// The table view shows every entry's tags, the client-side tag filter
+    /// only needs the selected ones.
+    fn needs_entry_tags(&self) -> bool {
+        self.view_mode == ViewMode::Table || self.client_tag_filter()
+    }
+
+    fn entry_tags_filter(&self) -> Vec<String> {
+        if self.view_mode == ViewMode::Table {
+            Vec::new()
+        } else {
+            self.selected_tags.filter_tags()
+        }
+    }
+
+    /// Loaded entry tags if they cover `filter`. An empty filter fetched
+    /// the tags of all entries, which covers any other.
+    fn entry_tags_for(&self, filter: &[String]) -> Option<&EntryTags> {
+        match &self.entry_tags {
+            Some((fetched_for, entry_tags)) if fetched_for.is_empty() || fetched_for == filter => Some(entry_tags),
+            _ => None,
+        }
+    }
+
+    fn hide_completed(&self) -> bool {
+        self.query.hidecompleted == Some(true)
+    }
+
+    /// `/search` ignores the date range, so search results are narrowed to it
+    /// here.
+    fn in_date_range(&self, entry: &Cache) -> bool {
+        if self.search_term().is_none() {
+            return true;
+        }
+        let date = match NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => return false,
+        };
+        self.query.start_date.is_none_or(|start| date >= start)
+            && self.query.end_date.is_none_or(|end| date <= end)
+    }
+
+    fn visible_entries(&self) -> Option<Vec<Cache>> {
+        let entries = self.entries.as_ref()?;
+        // `/search` doesn't take hidecompleted, and `completed` may be newer
+        // than the entries
+        let entries: Vec<Cache> = entries
+            .iter()
+            .filter(|e| !(self.hide_completed() && self.completed.contains(&e.entry_id)))
+            .filter(|e| self.in_date_range(e))
+            .filter(|e| self.search.as_ref().is_none_or(|s| s.matches(e, &self.completed)))
+            .cloned()
+            .collect();
+        if !self.client_tag_filter() {
+            let mut entries = entries;
+            self.sort.apply(&mut entries);
+            return Some(entries);
+        }
+        let entry_tags = self.entry_tags_for(&self.selected_tags.filter_tags())?;
+        let untagged = HashSet::new();
+        let mut visible: Vec<Cache> = entries
+            .iter()
+            .filter(|e| {
+                self.selected_tags
+                    .matches(entry_tags.get(&e.entry_id).unwrap_or(&untagged))
+            })
+            .cloned()
+            .collect();
+        self.sort.apply(&mut visible);
+        Some(visible)
+    }
+
+    fn view_select_button(&self) -> Html {
+        let (class, selecting) = if self.selecting {
+            ("sort-button sort-button-active", false)
+        } else {
+            ("sort-button", true)
+        };
+        html! {
+            <button class=format!("{} shadow-sm p-2 mb-3 rounded", class) title="Select cards, Shift-click for a range"
+                onclick=self.link.callback(move |_| AppMsg::Selecting(selecting))>{ "Select" }</button>
+        }
+    }
+
+    fn view_mode_switch(&self) -> Html {
+        let modes = ViewMode::ALL.iter().map(|&mode| {
+            let class = if self.view_mode == mode { "sort-button sort-button-active" } else { "sort-button" };
+            html! {
+                <button class=format!("{} shadow-sm p-2 mb-3 rounded", class)
+                    onclick=self.link.callback(move |_| AppMsg::ViewModeChange(mode))>{ mode.label() }</button>
+            }
+        });
+        let slider = if self.view_mode == ViewMode::Grid {
+            html! {
+                <input type="range" class="grid-size" title="Thumbnail size"
+                    min=GRID_SIZE_MIN.to_string() max=GRID_SIZE_MAX.to_string() step="20"
+                    value=self.grid_size.to_string()
+                    oninput=self.link.batch_callback(|e: InputData| e.value.parse().ok().map(AppMsg::GridSize))/>
+            }
+        } else {
+            html! {}
+        };
+        let on_group = self.link.batch_callback(|e: ChangeData| match e {
+            ChangeData::Select(select) => GroupBy::from_param(&select.value()).map(AppMsg::GroupByChange),
+            _ => None,
+        });
+        html! {
+            <span class="view-modes">
+                { for modes }
+                { slider }
+                <select class="group-by" title="Group by date" onchange=on_group>
+                    { for GroupBy::ALL.iter().map(|group_by| html! {
+                        <option value=group_by.as_param() selected=self.group_by == *group_by>{ group_by.label() }</option>
+                    }) }
+                </select>
+            </span>
+        }
+    }
+
+    fn view_batch_bar(&self) -> Html {
+        if !self.selecting {
+            return html! {};
+        }
+        let selected: Vec<Cache> = self
+            .visible_entries()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|e| self.selected.contains(&e.entry_id))
+            .collect();
+        html! {
+            <BatchBar entries=selected on_event=self.link.callback(AppMsg::Batch)
+                on_select_all=self.link.callback(|_| AppMsg::SelectAll)
+                on_clear=self.link.callback(|_| AppMsg::Selecting(true))/>
+        }
+    }
+
+    /// Gallery shortcuts only apply while the gallery is shown, `Detail`
+    /// handles its own.
+    fn key_action(&mut self, action: Action) -> ShouldRender {
+        match action {
+            Action::Help => {
+                self.show_help = !self.show_help;
+                return true;
+            }
+            Action::Close if self.show_help => {
+                self.show_help = false;
+                return true;
+            }
+            Action::Close if self.selecting => {
+                self.link.send_message(AppMsg::Selecting(false));
+                return false;
+            }
+            Action::Palette => {
+                self.show_palette = !self.show_palette;
+                return true;
+            }
+            _ => {}
+        }
+        let route = RouteService::<()>::new().get_route();
+        if !matches!(AppRoute::switch(route), Some(AppRoute::Gallery)) {
+            return false;
+        }
+        let ids: Vec<i32> = self
+            .visible_entries()
+            .unwrap_or_default()
+            .iter()
+            .map(|e| e.entry_id)
+            .collect();
+        let position = self.cursor.and_then(|id| ids.iter().position(|i| *i == id));
+        let move_to = |index: usize| ids.get(index).copied();
+        match action {
+            Action::Next => {
+                self.cursor = move_to(position.map_or(0, |p| (p + 1).min(ids.len().saturating_sub(1))));
+                true
+            }
+            Action::Prev => {
+                self.cursor = move_to(position.map_or(0, |p| p.saturating_sub(1)));
+                true
+            }
+            Action::First => {
+                self.cursor = move_to(0);
+                true
+            }
+            Action::Last => {
+                self.cursor = move_to(ids.len().saturating_sub(1));
+                // the rest can be loaded from there with `G` again
+                self.link.send_message(AppMsg::LoadMore);
+                true
+            }
+            Action::Open => {
+                if let Some(entry_id) = position.and(self.cursor) {
+                    let entry = self.entries.iter().flatten().find(|e| e.entry_id == entry_id).cloned();
+                    self.selected_entry = entry;
+                    self.router
+                        .send(RouteRequest::ChangeRoute(Route::from(AppRoute::Detail(entry_id))));
+                }
+                false
+            }
+            Action::ToggleCompleted => {
+                if let Some(entry_id) = position.and(self.cursor) {
+                    self.link.send_message(AppMsg::ToggleCompleted(entry_id));
+                }
+                false
+            }
+            Action::Tag => {
+                if let Some(entry_id) = position.and(self.cursor) {
+                    self.link.send_message(AppMsg::TagEntry(entry_id));
+                }
+                false
+            }
+            Action::Search => {
+                let input = document()
+                    .query_selector(".search-input")
+                    .ok()
+                    .flatten()
+                    .and_then(|e| e.dyn_into::<HtmlElement>().ok());
+                if let Some(input) = input {
+                    let _ = input.focus();
+                }
+                false
+            }
+            Action::Help | Action::Close | Action::Palette => false,
+        }
+    }
+
+    /// Mark an entry completed or not, shown right away and put back if the
+    /// request can't be sent or the server refuses it. Retries ask for the
+    /// same `state` again rather than toggling.
+    fn set_completed(&mut self, entry_id: i32, state: bool) {
+        let payload = CompletedPayload { entry_id, state };
+        let previous = self.completed.contains(&entry_id);
+        if state {
+            self.completed.insert(entry_id);
+        } else {
+            self.completed.remove(&entry_id);
+        }
+        let callback = {
+            let payload = payload.clone();
+            self.link
+                .callback(move |response| AppMsg::CompletedToggled(payload.clone(), response))
+        };
+        match self.api.submit_completed(&payload, callback) {
+            Ok(task) => {
+                self.completed_tasks.insert(entry_id, task);
+            }
+            Err(error) => {
+                if previous {
+                    self.completed.insert(entry_id);
+                } else {
+                    self.completed.remove(&entry_id);
+                }
+                let retry = self.link.callback_once(move |_| AppMsg::SetCompleted(entry_id, state));
+                notify_error(&mut self.notifier, "Could not update completed", &error, retry)
+            }
+        }
+    }
+
+    /// Hit sources for the current search term, if they have arrived.
+    fn search_hits(&self) -> HashMap<i32, SearchHit> {
+        match (&self.hits, self.search_term()) {
+            (Some((fetched_for, hits)), Some(term)) if fetched_for == term => hits.clone(),
+            _ => HashMap::new(),
+        }
+    }
+
+    /// Domains of the loaded entries, most common first, for autocomplete.
+    fn domains(&self) -> Vec<String> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for entry in self.entries.iter().flatten() {
+            if let Some(domain) = domain(entry) {
+                *counts.entry(domain).or_default() += 1;
+            }
+        }
+        let mut domains: Vec<(String, usize)> = counts.into_iter().collect();
+        domains.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        domains.into_iter().map(|(domain, _)| domain).collect()
+    }
+
+    fn gallery_url(&self) -> String {
+        let search = self.search.as_ref().map(|s| s.to_string());
+        gallery_url(&self.query, &self.selected_tags, self.sort, search.as_deref())
+    }
+
+    /// Push the current filters to the address bar, unless they are there
+    /// already (e.g. after back/forward).
+    fn sync_url(&mut self) {
+        let url = self.gallery_url();
+        let current = RouteService::<()>::new().get_route();
+        if current.route != url && matches!(AppRoute::switch(current), Some(AppRoute::Gallery)) {
+            let route = Route::from(url);
+            self.router.send(if self.typing {
+                RouteRequest::ReplaceRouteNoBroadcast(route)
+            } else {
+                RouteRequest::ChangeRouteNoBroadcast(route)
+            });
+        }
+    }
+
+    /// The search text with the part that didn't parse marked.
+    fn view_search_error(&self) -> Html {
+        let error = match &self.search_error {
+            Some(error) => error,
+            None => return html! {},
+        };
+        let text = &self.search_query;
+        let (before, rest) = text.split_at(error.start.min(text.len()));
+        let (marked, after) = rest.split_at((error.end - error.start).min(rest.len()));
+        html! {
+            <div class="search-error">
+                <span class="search-error-text">{ before }<mark>{ marked }</mark>{ after }</span>
+                <span class="search-error-message">{ &error.message }</span>
+            </div>
+        }
+    }
+
     fn view_navbar(&self) -> Html {
         html! {
             <nav class="navbar navbar-expand-lg navbar-light bg-light">
@@ -80,13 +547,24 @@ impl App {
                 <div class="collapse navbar-collapse" id="navbarNav">
                     <ul class="navbar-nav">
                         <li class="nav-item active">
-                            <Link route=AppRoute::Gallery><div class="nav-link">{ "Gallery" }</div></Link>
+                            // keeps the filters when coming back from another page
+                            <a href=self.gallery_url() onclick=self.link.callback(|e: MouseEvent| {
+                                e.prevent_default();
+                                AppMsg::ShowGallery
+                            })><div class="nav-link">{ "Gallery" }</div></a>
                         </li>
                         <li class="nav-item" accesskey="a">
                             <Link route=AppRoute::AddNote><div class="nav-link">{ "Create" }</div></Link>
                         </li>
                         <li class="nav-item" accesskey="d">
-                            <Link route=AppRoute::Detail><div class="nav-link">{ "Detail" }</div></Link>
+                            {
+                                match &self.selected_entry {
+                                    Some(entry) => html! {
+                                        <Link route=AppRoute::Detail(entry.entry_id)><div class="nav-link">{ "Detail" }</div></Link>
+                                    },
+                                    None => html! { <div class="nav-link disabled">{ "Detail" }</div> },
+                                }
+                            }
                         </li>
                         /*
                         <li class="nav-item" accesskey="s">
@@ -95,12 +573,13 @@ impl App {
                         <li class="nav-item" accesskey="q">
                             <Link route=AppRoute::Queue><div class="nav-link">{ "Queue" }</div></Link>
                         </li>
+                        */
                         <li class="nav-item" accesskey=",">
                             <Link route=AppRoute::Settings><div class="nav-link">{ "Settings" }</div></Link>
                         </li>
-                        */
                     </ul>
                 </div>
+                <SyncIndicator/>
             </nav>
         }
     }
@@ -111,25 +590,79 @@ impl Component for App {
     type Properties = ();
 
     fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let server = host().unwrap();
         log::info!("Creating component");
-        let cb = link.callback_once(|_: String| AppMsg::GetEntries);
-        cb.emit("".to_string()); // TODO - what's the right way to handle a message without parameters
-        log::info!("sent message");
-        // let kb_cb = link.callback(Msg::KeyDown);
-        let default_query = format!("http://{}/all/cache?limit=150", server).to_string();
+        link.send_message(AppMsg::GetEntries);
+        let mut query = CacheQuery::with_limit(150);
+        if prefs::get(prefs::HIDE_COMPLETED).as_deref() == Some("true") {
+            query.hidecompleted = Some(true);
+        }
+        let route = RouteService::<()>::new().get_route();
+        let mut selected_tags = TagSelection::default();
+        let mut sort = GallerySort::default();
+        let search = parse_gallery_url(&route.route, &mut query, &mut selected_tags, &mut sort);
+        let search = search_from_url(search, &mut query, &mut selected_tags);
+        if sort == GallerySort::default() {
+            // no sort in the url, use the last one picked
+            let saved = prefs::get(prefs::GALLERY_SORT);
+            let saved = saved.as_deref().and_then(|s| s.split_once(' '));
+            if let Some(saved) = saved.and_then(|(key, dir)| GallerySort::from_params(key, dir)) {
+                sort = saved;
+                sort.set_query(&mut query);
+            }
+        }
+        let route_changes = RouteAgentBridge::new(link.callback(AppMsg::RouteChanged));
+        let keys = KeyBindings::bridge(link.callback(AppMsg::Key));
         Self {
+            api: ApiClient::new(),
             cache_task: None,
+            page_task: None,
             tag_task: None,
+            generation: 0,
+            tags_threshold: None,
             entries: None,
+            has_more: false,
+            page_limit: None,
+            page_offset: 0,
             tags: None,
             selected_entry: None,
-            selected_tags: HashSet::new(),
+            selected_tags,
+            entry_tags: None,
+            entry_tags_task: None,
+            completed: HashSet::new(),
+            completed_task: None,
             link,
-            error: None,
-            default_query: default_query.clone(),
-            query: default_query.clone(),
-            search_query: String::from(""),
+            notifier: NotificationBus::dispatcher(),
+            router: RouteAgentDispatcher::new(),
+            _route_changes: route_changes,
+            offline: Offline::dispatcher(),
+            _keys: keys,
+            cursor: None,
+            show_help: false,
+            show_palette: false,
+            selecting: false,
+            selected: HashSet::new(),
+            select_anchor: None,
+            completed_tasks: HashMap::new(),
+            tag_tasks: HashMap::new(),
+            query,
+            sort,
+            view_mode: prefs::get(prefs::GALLERY_VIEW)
+                .and_then(|mode| ViewMode::from_param(&mode))
+                .unwrap_or_default(),
+            grid_size: prefs::get(prefs::GRID_SIZE)
+                .and_then(|size| size.parse().ok())
+                .unwrap_or(GRID_SIZE_DEFAULT),
+            group_by: prefs::get(prefs::GALLERY_GROUP)
+                .and_then(|group_by| GroupBy::from_param(&group_by))
+                .unwrap_or_default(),
+            search_query: search.as_ref().map(|s| s.to_string()).unwrap_or_default(),
+            search,
+            search_error: None,
+            search_timeout: None,
+            typing: false,
+            hits: None,
+            hits_task: None,
+            history: crate::saved_searches::history(),
             config: Config { tag_threshold: 10 },
         }
     }
@@ -139,72 +672,317 @@ impl Component for App {
     }
 
     fn update(&mut self, msg: Self::Message) -> bool {
-        let server = host().unwrap();
-        log::info!("host is {:?}", server);
         match msg {
             AppMsg::GetEntries => {
-                // define request
-                log::info!("submitting cache request: {:?}", self.query);
-                let request = Request::get(&self.query)
-                    .body(Nothing)
-                    .expect("Could not build request.");
-                // define callback
-                let callback = self.link.callback_once(
-                    |response: Response<Json<Result<Vec<Cache>, anyhow::Error>>>| {
-                        let Json(data) = response.into_body();
-                        AppMsg::ReceiveEntries(data)
-                    },
-                );
-                // task
-                let task = FetchService::fetch(request, callback).expect("failed to start request");
-                self.cache_task = Some(task);
-                // define request
-                log::info!("submitting tag request");
-                let request = Request::get(format!("http://{}/all/tags?min={}", server, self.config.tag_threshold))
-                    .body(Nothing)
-                    .expect("Could not build request.");
-                // define callback
-                let callback = self.link.callback_once(
-                    |response: Response<Json<Result<Vec<String>, anyhow::Error>>>| {
-                        let Json(data) = response.into_body();
-                        AppMsg::ReceiveTags(data)
-                    },
-                );
-                // task
-                let task = FetchService::fetch(request, callback).expect("failed to start request");
-                self.tag_task = Some(task);
+                // dropping the task aborts a request that is still in flight
+                self.cache_task = None;
+                self.page_task = None;
+                self.page_limit = None;
+                self.generation += 1;
+                self.sync_url();
+                let generation = self.generation;
+                log::info!("submitting cache request {}: {:?} {:?}", generation, self.query, self.search);
+                let callback = self
+                    .link
+                    .callback(move |response| AppMsg::ReceiveEntries(generation, response));
+                let task = match self.search_term() {
+                    Some(search) => self.api.search(search, callback),
+                    None => self.api.all_cache(&self.query, callback),
+                };
+                match task {
+                    Ok(task) => self.cache_task = Some(task),
+                    Err(error) => {
+                        let retry = self.link.callback(|_| AppMsg::GetEntries);
+                        notify_error(&mut self.notifier, "Could not load entries", &error, retry)
+                    }
+                }
+                if self.needs_entry_tags() {
+                    self.link.send_message(AppMsg::GetEntryTags);
+                }
+                if self.search_term().is_some() {
+                    self.link.send_message(AppMsg::GetHits);
+                }
+                self.link.send_message(AppMsg::GetCompleted);
+                self.link.send_message(AppMsg::GetTags);
                 true // redraw page
             }
-            AppMsg::ReceiveEntries(response) => {
+            AppMsg::GetTags => {
+                let threshold = self.config.tag_threshold;
+                if self.tags_threshold == Some(threshold) || self.tag_task.is_some() {
+                    return false;
+                }
+                log::info!("submitting tag request");
+                let callback = self
+                    .link
+                    .callback(move |response| AppMsg::ReceiveTags(threshold, response));
+                match self.api.all_tags(Some(threshold), callback) {
+                    Ok(task) => self.tag_task = Some(task),
+                    Err(error) => {
+                        let retry = self.link.callback(|_| AppMsg::GetTags);
+                        notify_error(&mut self.notifier, "Could not load tags", &error, retry)
+                    }
+                }
+                false
+            }
+            AppMsg::ReceiveEntries(generation, response) => {
+                if generation != self.generation {
+                    log::info!("dropping stale cache response {} (current {})", generation, self.generation);
+                    return false;
+                }
                 match response {
-                    Ok(result) => {
+                    Ok(mut result) => {
+                        if result.is_empty() && !self.typing {
+                            self.notifier.send(Notification::info("No entries match"));
+                        }
+                        // search results come back complete
+                        self.has_more = self.search_term().is_none()
+                            && self.query.limit.is_some_and(|limit| result.len() >= limit as usize);
+                        self.page_offset = result.len() as i32;
+                        // an entry must not show up twice, cards are keyed by entry id
+                        let mut seen = HashSet::new();
+                        result.retain(|entry| seen.insert(entry.entry_id));
                         self.entries = Some(result);
                     }
                     Err(error) => {
                         log::info!("cache receive error:");
                         log::info!("{}", &error.to_string());
-                        self.error = Some(error.to_string());
+                        let retry = self.link.callback(|_| AppMsg::GetEntries);
+                        notify_error(&mut self.notifier, "Could not load entries", &error, retry);
                     }
                 }
                 self.cache_task = None;
+                self.typing = false;
                 true
             }
-            AppMsg::ReceiveTags(response) => {
+            AppMsg::LoadMore => {
+                if !self.has_more || self.cache_task.is_some() || self.page_task.is_some() {
+                    return false;
+                }
+                let (cursor, query) = match self.next_page() {
+                    Some(page) => page,
+                    None => return false,
+                };
+                let generation = self.generation;
+                log::info!("submitting page request {} from {:?}", generation, cursor);
+                let callback = self
+                    .link
+                    .callback(move |response| AppMsg::ReceivePage(generation, cursor, response));
+                match self.api.all_cache(&query, callback) {
+                    Ok(task) => self.page_task = Some(task),
+                    Err(error) => {
+                        let retry = self.link.callback(|_| AppMsg::RetryPage);
+                        notify_error(&mut self.notifier, "Could not load more entries", &error, retry)
+                    }
+                }
+                true
+            }
+            AppMsg::RetryPage => {
+                self.has_more = true;
+                self.link.send_message(AppMsg::LoadMore);
+                false
+            }
+            AppMsg::ReceivePage(generation, cursor, response) => {
+                if generation != self.generation {
+                    log::info!("dropping stale page response {} (current {})", generation, self.generation);
+                    return false;
+                }
+                self.page_task = None;
+                let page = match response {
+                    Ok(page) => page,
+                    Err(error) => {
+                        // stop the scroll handler from retrying until asked to
+                        self.has_more = false;
+                        let retry = self.link.callback(|_| AppMsg::RetryPage);
+                        notify_error(&mut self.notifier, "Could not load more entries", &error, retry);
+                        return true;
+                    }
+                };
+                self.page_offset += page.len() as i32;
+                let limit = self.page_limit.or(self.query.limit);
+                let full = limit.is_some_and(|limit| page.len() >= limit as usize);
+                let entries = self.entries.get_or_insert_with(Vec::new);
+                // the cursor day comes back again
+                let mut seen: HashSet<i32> = entries.iter().map(|e| e.entry_id).collect();
+                let before = entries.len();
+                for entry in page {
+                    if seen.insert(entry.entry_id) {
+                        entries.push(entry);
+                    }
+                }
+                self.page_limit = None;
+                if entries.len() == before && full {
+                    // the whole page was loaded entries of the cursor day, ask
+                    // for it again with room for the rest of that day
+                    self.page_limit = limit.map(|limit| limit * 2);
+                    log::info!("more than a page on {:?}, retrying with limit {:?}", cursor, self.page_limit);
+                }
+                self.has_more = full;
+                true
+            }
+            AppMsg::ReceiveTags(threshold, response) => {
                 match response {
                     Ok(result) => {
                         self.tags = Some(result);
+                        self.tags_threshold = Some(threshold);
                     }
                     Err(error) => {
                         log::info!("tag receive error, error is:");
                         log::info!("{}", &error.to_string());
-                        self.error = Some(error.to_string());
+                        let retry = self.link.callback(|_| AppMsg::GetTags);
+                        notify_error(&mut self.notifier, "Could not load tags", &error, retry);
                     }
                 }
                 self.tag_task = None;
                 true
             }
-            AppMsg::KeyDown => {
-                log::info!("keydown event");
+            AppMsg::Key(action) => self.key_action(action),
+            AppMsg::ClosePalette => {
+                self.show_palette = false;
+                true
+            }
+            AppMsg::Selecting(selecting) => {
+                self.selecting = selecting;
+                self.selected.clear();
+                self.select_anchor = None;
+                true
+            }
+            AppMsg::Select(entry_id, range) => {
+                self.selecting = true;
+                let ids: Vec<i32> = self
+                    .visible_entries()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|e| e.entry_id)
+                    .collect();
+                let anchor = self.select_anchor.and_then(|id| ids.iter().position(|i| *i == id));
+                let clicked = ids.iter().position(|i| *i == entry_id);
+                match (range, anchor, clicked) {
+                    (true, Some(a), Some(c)) => self.selected.extend(&ids[a.min(c)..=a.max(c)]),
+                    _ => {
+                        if !self.selected.remove(&entry_id) {
+                            self.selected.insert(entry_id);
+                        }
+                    }
+                }
+                self.select_anchor = Some(entry_id);
+                true
+            }
+            AppMsg::SelectAll => {
+                let visible = self.visible_entries().unwrap_or_default();
+                self.selected.extend(visible.iter().map(|e| e.entry_id));
+                true
+            }
+            AppMsg::Batch(BatchEvent::Completed(entry_id, state)) => {
+                if state {
+                    self.completed.insert(entry_id)
+                } else {
+                    self.completed.remove(&entry_id)
+                }
+            }
+            AppMsg::Batch(BatchEvent::Tagged) => {
+                // same as tagging a single entry
+                self.entry_tags = None;
+                self.tags_threshold = None;
+                if self.needs_entry_tags() {
+                    self.link.send_message(AppMsg::GetEntryTags);
+                }
+                self.link.send_message(AppMsg::GetTags);
+                false
+            }
+            AppMsg::Command(command) => {
+                log::info!("palette command {:?}", command);
+                match command {
+                    Command::Gallery => self.link.send_message(AppMsg::ShowGallery),
+                    Command::Route(url) => self.router.send(RouteRequest::ChangeRoute(Route::from(url))),
+                    Command::Tag(tag) => {
+                        let selection = self.selected_tags.toggle(&tag, false);
+                        self.link.send_message_batch(vec![AppMsg::TagClick(selection), AppMsg::ShowGallery]);
+                    }
+                    Command::Open(entry_id) => {
+                        self.selected_entry = self.entries.iter().flatten().find(|e| e.entry_id == entry_id).cloned();
+                        self.router
+                            .send(RouteRequest::ChangeRoute(Route::from(AppRoute::Detail(entry_id))));
+                    }
+                    Command::HideCompleted(hide) => {
+                        self.link.send_message_batch(vec![AppMsg::HideCompleted(hide), AppMsg::ShowGallery]);
+                    }
+                    Command::Sort(sort) => {
+                        self.link.send_message_batch(vec![AppMsg::SortChange(sort), AppMsg::ShowGallery]);
+                    }
+                }
+                true
+            }
+            AppMsg::ToggleCompleted(entry_id) => {
+                let state = !self.completed.contains(&entry_id);
+                self.set_completed(entry_id, state);
+                true
+            }
+            AppMsg::SetCompleted(entry_id, state) => {
+                self.set_completed(entry_id, state);
+                true
+            }
+            AppMsg::CompletedToggled(payload, response) => {
+                self.completed_tasks.remove(&payload.entry_id);
+                match response {
+                    Ok(_) => false,
+                    Err(ApiError::Offline) => {
+                        self.offline.send(OfflineInput::Queue(OutboxItem::Completed(payload)));
+                        false
+                    }
+                    Err(error) => {
+                        if payload.state {
+                            self.completed.remove(&payload.entry_id);
+                        } else {
+                            self.completed.insert(payload.entry_id);
+                        }
+                        let retry = self
+                            .link
+                            .callback_once(move |_| AppMsg::SetCompleted(payload.entry_id, payload.state));
+                        notify_error(&mut self.notifier, "Could not update completed", &error, retry);
+                        true
+                    }
+                }
+            }
+            AppMsg::TagEntry(entry_id) => {
+                let entry = self.entries.iter().flatten().find(|e| e.entry_id == entry_id);
+                let title = entry.and_then(|e| e.content.clone()).unwrap_or_default();
+                let tags = match prompt_tags(&title) {
+                    Some(tags) => tags,
+                    None => return false,
+                };
+                let payload = TagsPayload { entry_id, tags };
+                let callback = {
+                    let payload = payload.clone();
+                    self.link
+                        .callback(move |response| AppMsg::EntryTagged(payload.clone(), response))
+                };
+                match self.api.submit_tags(&payload, callback) {
+                    Ok(task) => {
+                        self.tag_tasks.insert(entry_id, task);
+                    }
+                    Err(error) => self.notifier.send(Notification::error(format!("Could not add tags: {}", error))),
+                }
+                false
+            }
+            AppMsg::EntryTagged(payload, response) => {
+                self.tag_tasks.remove(&payload.entry_id);
+                match response {
+                    Ok(_) => {
+                        self.notifier.send(Notification::success(format!("Tagged {}", payload.tags.join(", "))));
+                        // tag counts and the client-side tag filter are stale
+                        self.entry_tags = None;
+                        self.tags_threshold = None;
+                        if self.needs_entry_tags() {
+                            self.link.send_message(AppMsg::GetEntryTags);
+                        }
+                        self.link.send_message(AppMsg::GetTags);
+                    }
+                    Err(ApiError::Offline) => {
+                        self.offline.send(OfflineInput::Queue(OutboxItem::Tags(payload)));
+                        self.notifier.send(Notification::info("Server unreachable, tags will be added once it is back"));
+                    }
+                    Err(error) => self.notifier.send(Notification::error(format!("Could not add tags: {}", error))),
+                }
                 false
             }
             AppMsg::CardClick(entry) => {
@@ -212,61 +990,283 @@ impl Component for App {
                 log::info!("selected entry is {:?}", self.selected_entry);
                 true
             }
-            AppMsg::TagClick(tag) => {
+            AppMsg::TagClick(selection) => {
                 log::info!("tag click event");
-                log::info!("{:?}", tag);
-                self.query = match tag {
-                    Some(tag_name) => {
-                        format!("http://{}/all/cache?sort=time&tag={}&limit=150", server, tag_name)
-                    }
-                    None => format!("http://{}/all/cache?sort=time&limit=150", server),
-                };
+                log::info!("{:?}", selection);
+                // the server narrows down to any of the included tags
+                self.query.tags = selection.include.clone();
+                self.selected_tags = selection;
                 log::info!("Query is: {:?}", &self.query);
-                // self.query = query.clone(); // TODO - make queryparams compose
                 self.link.send_message(AppMsg::GetEntries);
                 false
             }
             AppMsg::TimelineEvt(evt) => {
                 log::info!("Timeline event");
-                self.query = match evt {
-                    Some((dt_min, dt_max)) => {
-                        format!("http://{}/all/cache?sort=time&startDate={}&endDate={}&limit=150&sortDir=fwd", server, // TODO : sortDir doesn't work
-                            dt_min.format("%Y-%m-%d").to_string(), 
-                            dt_max.format("%Y-%m-%d").to_string())
-                    }
-                    None => format!("http://{}/all/cache?sort=time&limit=150", server),
+                let (start, end) = match evt {
+                    Some((dt_min, dt_max)) => (Some(dt_min.date()), Some(dt_max.date())),
+                    None => (None, None),
                 };
+                self.query.start_date = start;
+                self.query.end_date = end;
                 log::info!("Query is: {:?}", &self.query);
                 self.link.send_message(AppMsg::GetEntries);
                 false
             }
-            AppMsg::SortByDate => {
-                log::info!("sort date");
-                self.query = format!("http://{}/all/cache?sort=time&limit=150", server).to_string();
-                self.link.send_message(AppMsg::GetEntries);
+            AppMsg::FacetRemoved(change) => {
+                log::info!("facet removed {:?}", change);
+                match change {
+                    FacetChange::Search => {
+                        self.search = None;
+                        self.search_error = None;
+                        self.search_timeout = None;
+                        self.search_query.clear();
+                        self.link.send_message(AppMsg::GetEntries);
+                    }
+                    FacetChange::Dates => self.link.send_message(AppMsg::TimelineEvt(None)),
+                    FacetChange::Completed => self.link.send_message(AppMsg::HideCompleted(false)),
+                    FacetChange::Tags(selection) => self.link.send_message(AppMsg::TagClick(selection)),
+                    FacetChange::All => {
+                        prefs::set(prefs::HIDE_COMPLETED, "false");
+                        self.query.hidecompleted = None;
+                        self.query.start_date = None;
+                        self.query.end_date = None;
+                        self.query.tags = Vec::new();
+                        self.selected_tags = TagSelection::default();
+                        self.search = None;
+                        self.search_error = None;
+                        self.search_query.clear();
+                        self.link.send_message(AppMsg::GetEntries);
+                    }
+                }
                 true
             }
-            AppMsg::SortByUrl => {
-                log::info!("sort url");
-                self.query = format!("http://{}/all/cache?sort=url&limit=150", server).to_string();
-                self.link.send_message(AppMsg::GetEntries);
+            AppMsg::SortChange(sort) => {
+                log::info!("sort {:?}", sort);
+                let (key, dir) = sort.params();
+                prefs::set(prefs::GALLERY_SORT, &format!("{} {}", key, dir));
+                self.sort = sort;
+                let mut query = self.query.clone();
+                sort.set_query(&mut query);
+                // search results come back complete and are sorted here, the
+                // gallery pages in the new order from the start
+                if query != self.query && self.search_term().is_none() {
+                    self.query = query;
+                    self.link.send_message(AppMsg::GetEntries);
+                } else {
+                    self.query = query;
+                    self.sync_url();
+                }
                 true
             }
-            AppMsg::SearchKeyDown(keypress) => {
-                log::info!("search keydown {:?}", keypress.key());
-                if keypress.key() == "Enter" {
-                    self.link.send_message(AppMsg::SearchSubmit);
+            AppMsg::ViewModeChange(mode) => {
+                prefs::set(prefs::GALLERY_VIEW, mode.as_param());
+                self.view_mode = mode;
+                if self.needs_entry_tags() {
+                    self.link.send_message(AppMsg::GetEntryTags);
                 }
+                true
+            }
+            AppMsg::GridSize(size) => {
+                prefs::set(prefs::GRID_SIZE, &size.to_string());
+                self.grid_size = size;
+                true
+            }
+            AppMsg::GroupByChange(group_by) => {
+                prefs::set(prefs::GALLERY_GROUP, group_by.as_param());
+                self.group_by = group_by;
+                true
+            }
+            AppMsg::RunSearch(search) => {
+                self.search_query = search;
+                self.typing = false;
+                self.link.send_message(AppMsg::SearchSubmit);
                 false
             }
             AppMsg::SearchEdit(query) => {
                 self.search_query = query;
+                self.search_timeout = Some(TimeoutService::spawn(
+                    SEARCH_DEBOUNCE,
+                    self.link.callback(|_| AppMsg::SearchTyped),
+                ));
+                // the highlighted range no longer fits the text
+                self.search_error.take().is_some()
+            }
+            AppMsg::SearchTyped => {
+                self.search_timeout = None;
+                // errors and operators that become facets wait for Enter, the
+                // text is likely still being typed
+                let parsed = match parse(&self.search_query, Local::now().date_naive()) {
+                    Ok(parsed) if !parsed.has_facets() => parsed,
+                    _ => return false,
+                };
+                let search = if parsed.is_empty() { None } else { Some(parsed) };
+                if search == self.search {
+                    return false;
+                }
+                log::info!("search as you type: {:?}", search);
+                self.search = search;
+                self.typing = true;
+                self.link.send_message(AppMsg::GetEntries);
                 false
             }
             AppMsg::SearchSubmit => {
-                self.query = format!("http://{}/search/{}", server, encode(self.search_query.trim()).to_string());
-                log::info!("Query: {}", &self.query);
+                self.search_timeout = None;
+                // submitted, not a search-as-you-type step
+                self.typing = false;
+                let parsed = match parse(&self.search_query, Local::now().date_naive()) {
+                    Ok(parsed) => parsed,
+                    Err(error) => {
+                        log::info!("search syntax error: {:?}", error);
+                        self.search_error = Some(error);
+                        return true;
+                    }
+                };
+                self.search_error = None;
+                let text = self.search_query.trim();
+                if !text.is_empty() {
+                    self.history = crate::saved_searches::remember(text);
+                }
+                // operators with a gallery filter become facets, the rest stays in the box
+                self.search = parsed.apply(&mut self.query, &mut self.selected_tags);
+                self.search_query = self.search.as_ref().map(|s| s.to_string()).unwrap_or_default();
+                log::info!("Query: {:?}", &self.search);
                 self.link.send_message(AppMsg::GetEntries);
+                true
+            }
+            AppMsg::GetHits => {
+                let term = match self.search_term() {
+                    Some(term) => term.to_string(),
+                    None => return false,
+                };
+                if self.hits.as_ref().is_some_and(|(fetched_for, _)| *fetched_for == term) {
+                    return false;
+                }
+                let callback = {
+                    let term = term.clone();
+                    self.link
+                        .callback(move |response| AppMsg::ReceiveHits(term.clone(), response))
+                };
+                match self.api.search_hits(&term, callback) {
+                    Ok(task) => self.hits_task = Some(task),
+                    // the indicator is optional, the results are there without it
+                    Err(error) => log::info!("could not load search hits: {}", error),
+                }
+                false
+            }
+            AppMsg::ReceiveHits(term, response) => {
+                self.hits_task = None;
+                match response {
+                    Ok(hits) => {
+                        let hits = hits.into_iter().map(|hit| (hit.entry_id, hit)).collect();
+                        self.hits = Some((term, hits));
+                        true
+                    }
+                    Err(error) => {
+                        log::info!("could not load search hits: {}", error);
+                        false
+                    }
+                }
+            }
+            AppMsg::GetEntryTags => {
+                let tags = self.entry_tags_filter();
+                if self.entry_tags_for(&tags).is_some() {
+                    return false;
+                }
+                let callback = {
+                    let tags = tags.clone();
+                    self.link
+                        .callback(move |response| AppMsg::ReceiveEntryTags(tags.clone(), response))
+                };
+                match self.api.link_entry_tags(&tags, callback) {
+                    Ok(task) => self.entry_tags_task = Some(task),
+                    Err(error) => {
+                        let retry = self.link.callback(|_| AppMsg::GetEntryTags);
+                        notify_error(&mut self.notifier, "Could not load entry tags", &error, retry)
+                    }
+                }
+                false
+            }
+            AppMsg::ReceiveEntryTags(tags, response) => {
+                self.entry_tags_task = None;
+                match response {
+                    Ok(rows) => {
+                        let mut entry_tags = EntryTags::new();
+                        for row in rows {
+                            if let Some(tag) = row.tag {
+                                entry_tags.entry(row.entry_id).or_default().insert(tag);
+                            }
+                        }
+                        self.entry_tags = Some((tags, entry_tags));
+                    }
+                    Err(error) => {
+                        let retry = self.link.callback(|_| AppMsg::GetEntryTags);
+                        notify_error(&mut self.notifier, "Could not load entry tags", &error, retry)
+                    }
+                }
+                true
+            }
+            AppMsg::GetCompleted => {
+                let callback = self.link.callback(AppMsg::ReceiveCompleted);
+                match self.api.all_completed(callback) {
+                    Ok(task) => self.completed_task = Some(task),
+                    Err(error) => {
+                        let retry = self.link.callback(|_| AppMsg::GetCompleted);
+                        notify_error(&mut self.notifier, "Could not load completed entries", &error, retry)
+                    }
+                }
+                false
+            }
+            AppMsg::ReceiveCompleted(response) => {
+                self.completed_task = None;
+                match response {
+                    Ok(ids) => self.completed = ids.into_iter().collect(),
+                    Err(error) => {
+                        let retry = self.link.callback(|_| AppMsg::GetCompleted);
+                        notify_error(&mut self.notifier, "Could not load completed entries", &error, retry)
+                    }
+                }
+                true
+            }
+            AppMsg::HideCompleted(hide) => {
+                prefs::set(prefs::HIDE_COMPLETED, if hide { "true" } else { "false" });
+                self.query.hidecompleted = if hide { Some(true) } else { None };
+                self.link.send_message(AppMsg::GetEntries);
+                true
+            }
+            AppMsg::RouteChanged(route) => {
+                // back/forward between gallery urls
+                if !matches!(AppRoute::switch(route.clone()), Some(AppRoute::Gallery)) {
+                    return false;
+                }
+                // may have been changed on a detail page
+                self.link.send_message(AppMsg::GetCompleted);
+                let mut query = self.query.clone();
+                let mut selected_tags = TagSelection::default();
+                let mut sort = GallerySort::default();
+                let search = parse_gallery_url(&route.route, &mut query, &mut selected_tags, &mut sort);
+                let search = search_from_url(search, &mut query, &mut selected_tags);
+                if query == self.query
+                    && selected_tags == self.selected_tags
+                    && sort == self.sort
+                    && search == self.search
+                {
+                    return false;
+                }
+                log::info!("filters from url: {:?} {:?} {:?}", query, selected_tags, search);
+                self.query = query;
+                self.selected_tags = selected_tags;
+                self.sort = sort;
+                self.search_query = search.as_ref().map(|s| s.to_string()).unwrap_or_default();
+                self.search = search;
+                self.search_error = None;
+                self.link.send_message(AppMsg::GetEntries);
+                true
+            }
+            AppMsg::ShowGallery => {
+                let url = self.gallery_url();
+                if RouteService::<()>::new().get_route().route != url {
+                    self.router.send(RouteRequest::ChangeRoute(Route::from(url)));
+                }
                 false
             }
         }
@@ -275,32 +1275,69 @@ impl Component for App {
     fn view(&self) -> Html {
         let empty_vec = &[].to_vec();
         let exist_tags = self.tags.as_ref().unwrap_or(empty_vec);
-        let card_callback = self.link.callback(move |card| AppMsg::CardClick(card));
-        let tag_callback = self.link.callback(move |tag| AppMsg::TagClick(tag));
-        let timeline_callback = self.link.callback(move |dt| AppMsg::TimelineEvt(dt));
-
-        let button_class = "sort-button shadow-sm p-3 mb-5 bg-white rounded";
+        let card_callback = self.link.callback(AppMsg::CardClick);
+        let load_more_callback = self.link.callback(|_| AppMsg::LoadMore);
+        let tag_callback = self.link.callback(AppMsg::TagClick);
+        let table_tags = match self.view_mode {
+            ViewMode::Table => self.entry_tags_for(&[]).cloned().unwrap_or_default(),
+            _ => EntryTags::new(),
+        };
+        let timeline_callback = self.link.callback(AppMsg::TimelineEvt);
 
+        let hide_completed = self.hide_completed();
         let gallery = html! {
             <div>
-                /*
-                    <button class=button_class onclick=self.link.callback(|m| { 
-                        AppMsg::SortByDate
-                        })> {"▼ Date"}</button>
-                */
-                <input type="text" class="search-input shadow-sm p-3 mb-5 bg-white rounded" placeholder="Search" accesskey="/" 
-                oninput = { self.link.callback(move |e: InputData| AppMsg::SearchEdit(e.value)) }
-                onkeydown = { self.link.callback(move |e: KeyboardEvent| AppMsg::SearchKeyDown(e)) }
-                />
+                <SearchBox value=self.search_query.clone() invalid=self.search_error.is_some()
+                    history=self.history.clone() tags=exist_tags.clone() domains=self.domains()
+                    on_input=self.link.callback(AppMsg::SearchEdit)
+                    on_submit=self.link.callback(|_| AppMsg::SearchSubmit)/>
+                { self.view_search_error() }
                 <Timeline timeline_callback = timeline_callback/>
+                <Facets search=self.search.as_ref().map(|s| s.to_string()) start_date=self.query.start_date end_date=self.query.end_date
+                    hide_completed=hide_completed selected_tags=self.selected_tags.clone()
+                    on_change=self.link.callback(AppMsg::FacetRemoved)/>
+                <div class="sort-toolbar">
+                    { for SortKey::ALL.iter().map(|&key| {
+                        let active = self.sort.key == key;
+                        let arrow = match (active, self.sort.dir) {
+                            (false, _) => "",
+                            (true, SortDir::Fwd) => "▲ ",
+                            (true, SortDir::Rev) => "▼ ",
+                        };
+                        let class = if active { "sort-button sort-button-active" } else { "sort-button" };
+                        let next = self.sort.toggle(key);
+                        html! {
+                            <button class=format!("{} shadow-sm p-2 mb-3 rounded", class)
+                                onclick=self.link.callback(move |_| AppMsg::SortChange(next))>
+                                { format!("{}{}", arrow, key.label()) }
+                            </button>
+                        }
+                    }) }
+                    { self.view_select_button() }
+                    { self.view_mode_switch() }
+                </div>
+                { self.view_batch_bar() }
                 <p/>
                 <div class="twocol">
-                    <Cards entries=self.entries.clone() card_click_callback=card_callback/>
+                    <Cards entries=self.visible_entries() completed=self.completed.clone() card_click_callback=card_callback
+                        has_more=self.has_more loading_more=self.page_task.is_some()
+                        load_more_callback=load_more_callback
+                        highlight=self.search.as_ref().map(|s| s.terms.clone()).unwrap_or_default()
+                        hits=self.search_hits() cursor=self.cursor
+                        selecting=self.selecting selected=self.selected.clone()
+                        select_callback=self.link.callback(|(entry_id, range)| AppMsg::Select(entry_id, range))
+                        view_mode=self.view_mode grid_size=self.grid_size group_by=self.group_by
+                        entry_tags=table_tags sort=self.sort sort_callback=self.link.callback(AppMsg::SortChange)/>
                     <div>
-                        <Tags tags=exist_tags.clone() tag_click_callback=tag_callback/>
-                        //<p/>
-                        //<input type="checkbox" id="hidecompleted" name="hidecompleted"/>
-                        //<label style="height:10%; margin-left: 10px"> {"Hide Completed"} </label>
+                        <SavedSearches current_url=self.gallery_url() history=self.history.clone()
+                            on_search=self.link.callback(AppMsg::RunSearch)/>
+                        <Tags tags=exist_tags.clone() selected=self.selected_tags.clone() tag_click_callback=tag_callback/>
+                        <div class="hide-completed">
+                            <input type="checkbox" id="hidecompleted" name="hidecompleted"
+                                checked=hide_completed
+                                onchange=self.link.callback(move |_| AppMsg::HideCompleted(!hide_completed))/>
+                            <label for="hidecompleted" style="height:10%; margin-left: 10px"> {"Hide Completed"} </label>
+                        </div>
                     </div>
                 </div>
             </div>
@@ -312,16 +1349,40 @@ impl Component for App {
         let render = Router::render(move |switch: AppRoute| match switch {
             AppRoute::Gallery => gallery.clone(),
             AppRoute::AddNote => html! { <AddNote/> },
-            AppRoute::Detail => html! { <Detail entry=entry.clone() /> },
+            AppRoute::Detail(entry_id) => html! { <Detail entry_id=entry_id entry=entry.clone() /> },
             AppRoute::Space => html! { <Space /> },
             AppRoute::Queue => html! { <Queue /> },
             AppRoute::Settings => html! { <Settings/> },
         });
 
         html! {
-            <div class="main-outer" onkeydown={ self.link.callback(move |e: KeyboardEvent|
-                { e.stop_propagation(); AppMsg::KeyDown })}>
+            <div class="main-outer">
                 { self.view_navbar() }
+                {
+                    if self.show_help {
+                        html! { <KeyHelp on_close=self.link.callback(|_| AppMsg::Key(Action::Help))/> }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    if self.show_palette {
+                        let items = palette::items(
+                            exist_tags,
+                            &self.selected_tags.include,
+                            self.entries.as_deref().unwrap_or_default(),
+                            self.query.hidecompleted == Some(true),
+                            self.sort,
+                        );
+                        html! {
+                            <Palette items=items on_command=self.link.callback(AppMsg::Command)
+                                on_close=self.link.callback(|_| AppMsg::ClosePalette)/>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                <Toasts/>
                 <div class="main-inner">
                     <div class="main-top">
                     /*
@@ -1,3 +1,7 @@
+use crate::api::CacheQuery;
+use crate::sort::GallerySort;
+use crate::tags::{TagMode, TagSelection};
+use chrono::NaiveDate;
 use yew_router::prelude::*;
 
 #[derive(Switch, Debug, Clone)]
@@ -10,8 +14,8 @@ pub enum AppRoute {
     */
     #[to = "/frontend/addnote"]
     AddNote,
-    #[to = "/frontend/detail"]
-    Detail,
+    #[to = "/frontend/detail/{entry_id}"]
+    Detail(i32),
     #[to = "/frontend/space"]
     Space,
     #[to = "/frontend/queue"]
@@ -21,3 +25,217 @@ pub enum AppRoute {
     #[to = "/frontend/settings"]
     Settings,
 }
+
+// gallery filters in the address bar, e.g.
+// /frontend/index.html?tag=ml&start=2021-01-01&q=attention
+
+const GALLERY_PATH: &str = "/frontend/index.html";
+
+/// Gallery url for the given filters. Only what can be chosen in the
+/// gallery is written, the page size stays out of the address bar. Included
+/// tags come from `tags`, `query.tags` only holds what is sent to the server.
+pub fn gallery_url(
+    query: &CacheQuery,
+    tags: &TagSelection,
+    sort: GallerySort,
+    search: Option<&str>,
+) -> String {
+    let mut params = url::form_urlencoded::Serializer::new(String::new());
+    for tag in &tags.include {
+        params.append_pair("tag", tag);
+    }
+    for tag in &tags.exclude {
+        params.append_pair("not", tag);
+    }
+    if tags.mode == TagMode::All {
+        params.append_pair("tagmode", "all");
+    }
+    if let Some(start) = query.start_date {
+        params.append_pair("start", &start.format("%Y-%m-%d").to_string());
+    }
+    if let Some(end) = query.end_date {
+        params.append_pair("end", &end.format("%Y-%m-%d").to_string());
+    }
+    if let Some(search) = search {
+        params.append_pair("q", search);
+    }
+    if sort != GallerySort::default() {
+        let (key, dir) = sort.params();
+        params.append_pair("sort", key);
+        params.append_pair("dir", dir);
+    }
+    let params = params.finish();
+    if params.is_empty() {
+        GALLERY_PATH.to_string()
+    } else {
+        format!("{}?{}", GALLERY_PATH, params)
+    }
+}
+
+/// Reverse of `gallery_url`: replaces the address bar filters in `query`,
+/// `tags` and `sort` with the ones in `url` and returns the search text, if
+/// any. Unknown or malformed params are ignored.
+pub fn parse_gallery_url(
+    url: &str,
+    query: &mut CacheQuery,
+    tags: &mut TagSelection,
+    sort: &mut GallerySort,
+) -> Option<String> {
+    let params = url
+        .split_once('?')
+        .map(|(_, params)| params.split('#').next().unwrap_or(""))
+        .unwrap_or("");
+    let day = |value: &str| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok();
+    *tags = TagSelection::default();
+    query.start_date = None;
+    query.end_date = None;
+    let mut search = None;
+    let (mut sort_key, mut sort_dir) = (None, None);
+    for (key, value) in url::form_urlencoded::parse(params.as_bytes()) {
+        match key.as_ref() {
+            "tag" => tags.include.push(value.into_owned()),
+            "not" => tags.exclude.push(value.into_owned()),
+            "tagmode" if value == "all" => tags.mode = TagMode::All,
+            "start" => query.start_date = day(&value),
+            "end" => query.end_date = day(&value),
+            "q" if !value.trim().is_empty() => search = Some(value.into_owned()),
+            "sort" => sort_key = Some(value.into_owned()),
+            "dir" => sort_dir = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+    *sort = match (sort_key, sort_dir) {
+        (Some(key), Some(dir)) => GallerySort::from_params(&key, &dir).unwrap_or_default(),
+        _ => GallerySort::default(),
+    };
+    query.tags = tags.include.clone();
+    sort.set_query(query);
+    search
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{SortBy, SortDir};
+    use crate::sort::SortKey;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    // (query, tags, sort, search) parsed from `url` onto defaults
+    fn parsed(url: &str) -> (CacheQuery, TagSelection, GallerySort, Option<String>) {
+        let mut query = CacheQuery::with_limit(50);
+        let mut tags = TagSelection::default();
+        let mut sort = GallerySort::default();
+        let search = parse_gallery_url(url, &mut query, &mut tags, &mut sort);
+        (query, tags, sort, search)
+    }
+
+    #[test]
+    fn empty_url() {
+        assert_eq!(
+            gallery_url(&CacheQuery::default(), &TagSelection::default(), GallerySort::default(), None),
+            "/frontend/index.html"
+        );
+        let (query, tags, sort, search) = parsed("/frontend/index.html");
+        assert_eq!(tags, TagSelection::default());
+        assert_eq!(sort, GallerySort::default());
+        assert_eq!(search, None);
+        assert_eq!(query.start_date, None);
+        assert_eq!(query.sort, Some(SortBy::Time));
+    }
+
+    #[test]
+    fn round_trip() {
+        let tags = TagSelection {
+            include: strings(&["ml", "c++ & rust", "ünïcode"]),
+            exclude: strings(&["news", "a=b?c#d"]),
+            mode: TagMode::All,
+        };
+        let query = CacheQuery {
+            start_date: Some(date(2021, 1, 1)),
+            end_date: Some(date(2021, 12, 31)),
+            ..CacheQuery::default()
+        };
+        let sort = GallerySort {
+            key: SortKey::Title,
+            dir: SortDir::Rev,
+        };
+        let search = "tag:\"x y\" attention & more #1";
+        let url = gallery_url(&query, &tags, sort, Some(search));
+        assert!(!url[1..].contains(['#', ' ']), "unescaped {}", url);
+
+        let (parsed_query, parsed_tags, parsed_sort, parsed_search) = parsed(&url);
+        assert_eq!(parsed_tags, tags);
+        assert_eq!(parsed_sort, sort);
+        assert_eq!(parsed_search.as_deref(), Some(search));
+        assert_eq!(parsed_query.start_date, query.start_date);
+        assert_eq!(parsed_query.end_date, query.end_date);
+        // what goes to the server follows the address bar
+        assert_eq!(parsed_query.tags, tags.include);
+        assert_eq!(parsed_query.sort, Some(SortBy::Title));
+        assert_eq!(parsed_query.sortdir, Some(SortDir::Rev));
+        assert_eq!(parsed_query.limit, Some(50));
+    }
+
+    #[test]
+    fn parses_hand_written_urls() {
+        let (query, tags, sort, search) =
+            parsed("/frontend/index.html?tag=a+b&tag=c%2Cd&not=e&start=2021-06-01&q=attention#top");
+        assert_eq!(tags.include, strings(&["a b", "c,d"]));
+        assert_eq!(tags.exclude, strings(&["e"]));
+        assert_eq!(tags.mode, TagMode::Any);
+        assert_eq!(query.start_date, Some(date(2021, 6, 1)));
+        assert_eq!(query.end_date, None);
+        assert_eq!(sort, GallerySort::default());
+        assert_eq!(search.as_deref(), Some("attention"));
+    }
+
+    #[test]
+    fn ignores_unknown_and_malformed_params() {
+        let (query, tags, sort, search) = parsed(
+            "/frontend/index.html?limit=5&foo=bar&start=2021-13-01&end=yesterday&tagmode=some\
+             &sort=size&dir=asc&q=%20%20&%zz=1",
+        );
+        assert_eq!(tags, TagSelection::default());
+        assert_eq!(query.start_date, None);
+        assert_eq!(query.end_date, None);
+        assert_eq!(query.limit, Some(50));
+        assert_eq!(sort, GallerySort::default());
+        assert_eq!(search, None);
+
+        // a sort key needs its direction
+        let (_, _, sort, _) = parsed("/frontend/index.html?sort=title");
+        assert_eq!(sort, GallerySort::default());
+    }
+
+    #[test]
+    fn replaces_previous_filters() {
+        let mut query = CacheQuery {
+            start_date: Some(date(2020, 1, 1)),
+            end_date: Some(date(2020, 2, 1)),
+            tags: strings(&["old"]),
+            ..CacheQuery::default()
+        };
+        let mut tags = TagSelection {
+            include: strings(&["old"]),
+            ..TagSelection::default()
+        };
+        let mut sort = GallerySort {
+            key: SortKey::Domain,
+            dir: SortDir::Fwd,
+        };
+        let search = parse_gallery_url("/frontend/index.html?end=2021-02-03", &mut query, &mut tags, &mut sort);
+        assert_eq!(search, None);
+        assert_eq!(tags, TagSelection::default());
+        assert!(query.tags.is_empty());
+        assert_eq!(query.start_date, None);
+        assert_eq!(query.end_date, Some(date(2021, 2, 3)));
+        assert_eq!(sort, GallerySort::default());
+    }
+}
@@ -0,0 +1,363 @@
+use crate::api::*;
+use crate::app_router::AppRoute;
+use crate::offline::*;
+use crate::tags::parse_tags;
+use std::collections::VecDeque;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlElement;
+use yew::agent::{Dispatched, Dispatcher};
+use yew::prelude::*;
+use yew::utils::{document, window};
+use yew::Properties;
+use yew_router::prelude::*;
+
+/// Something done to every selected entry, one request each.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchAction {
+    Completed(bool),
+    AddTags(Vec<String>),
+    RemoveTags(Vec<String>),
+}
+
+impl BatchAction {
+    fn describe(&self) -> String {
+        match self {
+            BatchAction::Completed(true) => "Marking completed".to_string(),
+            BatchAction::Completed(false) => "Marking not completed".to_string(),
+            BatchAction::AddTags(tags) => format!("Adding {}", tags.join(", ")),
+            BatchAction::RemoveTags(tags) => format!("Removing {}", tags.join(", ")),
+        }
+    }
+
+    fn request(&self, api: &ApiClient, entry_id: i32, callback: Callback<ApiResult<i64>>) -> ApiResult<ApiTask> {
+        match self {
+            BatchAction::Completed(state) => {
+                api.submit_completed(&CompletedPayload { entry_id, state: *state }, callback)
+            }
+            BatchAction::AddTags(tags) => api.submit_tags(&TagsPayload { entry_id, tags: tags.clone() }, callback),
+            BatchAction::RemoveTags(tags) => {
+                api.remove_tags(&TagsPayload { entry_id, tags: tags.clone() }, callback)
+            }
+        }
+    }
+
+    // replayed once the server is back, see `Offline`
+    fn outbox_item(&self, entry_id: i32) -> OutboxItem {
+        match self {
+            BatchAction::Completed(state) => OutboxItem::Completed(CompletedPayload { entry_id, state: *state }),
+            BatchAction::AddTags(tags) => OutboxItem::Tags(TagsPayload { entry_id, tags: tags.clone() }),
+            BatchAction::RemoveTags(tags) => OutboxItem::RemoveTags(TagsPayload { entry_id, tags: tags.clone() }),
+        }
+    }
+}
+
+/// Sent to the gallery for every entry the server accepted (or that was
+/// queued offline), so it can update its badges and tags.
+#[derive(Debug, Clone)]
+pub enum BatchEvent {
+    Completed(i32, bool),
+    Tagged,
+}
+
+#[derive(Debug)]
+struct Run {
+    action: BatchAction,
+    pending: VecDeque<i32>,
+    total: usize,
+    done: usize,
+    queued: usize,
+    // entry id, error
+    failed: Vec<(i32, String)>,
+}
+
+pub enum BatchMsg {
+    TagsEdit(String),
+    Start(BatchAction),
+    Next,
+    Response(i32, ApiResult<i64>),
+    Cancel,
+    RetryFailed,
+    Dismiss,
+    Export,
+    OpenTabs,
+}
+
+/// Action bar for the selected gallery cards. Requests go out one at a
+/// time, with a progress bar and the failures listed once done.
+pub struct BatchBar {
+    link: ComponentLink<Self>,
+    props: BatchProps,
+    tags: String,
+    run: Option<Run>,
+    task: Option<ApiTask>,
+    api: ApiClient,
+    offline: Dispatcher<Offline>,
+}
+
+#[derive(Properties, Clone)]
+pub struct BatchProps {
+    /// the selected entries, in gallery order
+    pub entries: Vec<Cache>,
+    pub on_event: Callback<BatchEvent>,
+    pub on_select_all: Callback<()>,
+    pub on_clear: Callback<()>,
+}
+
+impl BatchBar {
+    fn title(&self, entry_id: i32) -> String {
+        self.props
+            .entries
+            .iter()
+            .find(|e| e.entry_id == entry_id)
+            .and_then(|e| e.content.clone())
+            .unwrap_or_else(|| format!("Entry {}", entry_id))
+    }
+
+    fn start(&mut self, action: BatchAction, ids: Vec<i32>) {
+        self.run = Some(Run {
+            action,
+            total: ids.len(),
+            pending: ids.into(),
+            done: 0,
+            queued: 0,
+            failed: Vec::new(),
+        });
+        self.link.send_message(BatchMsg::Next);
+    }
+
+    fn export(&self) {
+        let json = match serde_json::to_string_pretty(&self.props.entries) {
+            Ok(json) => json,
+            Err(e) => {
+                log::info!("could not export: {}", e);
+                return;
+            }
+        };
+        let name = format!("openmemex-{}.json", chrono::Local::now().format("%Y-%m-%d"));
+        let link = document()
+            .create_element("a")
+            .ok()
+            .and_then(|a| a.dyn_into::<HtmlElement>().ok());
+        if let Some(link) = link {
+            let href = format!("data:application/json;charset=utf-8,{}", urlencoding::encode(&json));
+            let _ = link.set_attribute("href", &href);
+            let _ = link.set_attribute("download", &name);
+            link.click();
+        }
+    }
+
+    fn open_tabs(&self) {
+        // browsers may block all but the first, the url bar says so
+        for entry in &self.props.entries {
+            let url = entry
+                .url
+                .clone()
+                .unwrap_or_else(|| Route::<()>::from(AppRoute::Detail(entry.entry_id)).route);
+            if let Err(e) = window().open_with_url_and_target(&url, "_blank") {
+                log::info!("could not open {}: {:?}", url, e);
+            }
+        }
+    }
+
+    fn view_run(&self, run: &Run) -> Html {
+        let finished = run.pending.is_empty() && self.task.is_none();
+        let mut summary = format!("{}: {}/{}", run.action.describe(), run.done, run.total);
+        if run.queued > 0 {
+            summary.push_str(&format!(", {} queued until the server is back", run.queued));
+        }
+        if !run.failed.is_empty() {
+            summary.push_str(&format!(", {} failed", run.failed.len()));
+        }
+        html! {
+            <div class="batch-run">
+                <progress value=run.done.to_string() max=run.total.to_string()/>
+                <span class="batch-summary">{ summary }</span>
+                {
+                    if finished {
+                        html! {
+                            <>
+                                {
+                                    if run.failed.is_empty() {
+                                        html! {}
+                                    } else {
+                                        html! {
+                                            <button class="batch-button"
+                                                onclick=self.link.callback(|_| BatchMsg::RetryFailed)>{ "Retry failed" }</button>
+                                        }
+                                    }
+                                }
+                                <button class="batch-button" onclick=self.link.callback(|_| BatchMsg::Dismiss)>{ "Done" }</button>
+                            </>
+                        }
+                    } else {
+                        html! { <button class="batch-button" onclick=self.link.callback(|_| BatchMsg::Cancel)>{ "Cancel" }</button> }
+                    }
+                }
+                <ul class="batch-failures">
+                    { for run.failed.iter().map(|(entry_id, error)| html! {
+                        <li>{ format!("{}: {}", self.title(*entry_id), error) }</li>
+                    }) }
+                </ul>
+            </div>
+        }
+    }
+}
+
+impl Component for BatchBar {
+    type Message = BatchMsg;
+    type Properties = BatchProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            link,
+            props,
+            tags: String::new(),
+            run: None,
+            task: None,
+            api: ApiClient::new(),
+            offline: Offline::dispatcher(),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            BatchMsg::TagsEdit(tags) => {
+                self.tags = tags;
+                true
+            }
+            BatchMsg::Start(action) => {
+                if self.task.is_some() {
+                    return false;
+                }
+                let ids = self.props.entries.iter().map(|e| e.entry_id).collect();
+                self.start(action, ids);
+                true
+            }
+            BatchMsg::Next => {
+                let run = match &mut self.run {
+                    Some(run) => run,
+                    None => return false,
+                };
+                let entry_id = match run.pending.pop_front() {
+                    Some(entry_id) => entry_id,
+                    None => {
+                        if matches!(run.action, BatchAction::AddTags(_) | BatchAction::RemoveTags(_))
+                            && run.done > run.failed.len()
+                        {
+                            self.props.on_event.emit(BatchEvent::Tagged);
+                        }
+                        return true;
+                    }
+                };
+                let callback = self.link.callback(move |response| BatchMsg::Response(entry_id, response));
+                match run.action.request(&self.api, entry_id, callback) {
+                    Ok(task) => self.task = Some(task),
+                    Err(error) => {
+                        run.done += 1;
+                        run.failed.push((entry_id, error.to_string()));
+                        self.link.send_message(BatchMsg::Next);
+                    }
+                }
+                true
+            }
+            BatchMsg::Response(entry_id, response) => {
+                self.task = None;
+                let run = match &mut self.run {
+                    Some(run) => run,
+                    None => return false,
+                };
+                run.done += 1;
+                match response {
+                    Ok(_) => {}
+                    Err(ApiError::Offline) => {
+                        run.queued += 1;
+                        self.offline.send(OfflineInput::Queue(run.action.outbox_item(entry_id)));
+                    }
+                    Err(error) => {
+                        run.failed.push((entry_id, error.to_string()));
+                        self.link.send_message(BatchMsg::Next);
+                        return true;
+                    }
+                }
+                if let BatchAction::Completed(state) = run.action {
+                    self.props.on_event.emit(BatchEvent::Completed(entry_id, state));
+                }
+                self.link.send_message(BatchMsg::Next);
+                true
+            }
+            BatchMsg::Cancel => {
+                self.task = None;
+                if let Some(run) = &mut self.run {
+                    // the request in flight may or may not have made it
+                    run.total = run.done;
+                    run.pending.clear();
+                }
+                self.link.send_message(BatchMsg::Next);
+                true
+            }
+            BatchMsg::RetryFailed => {
+                if let Some(run) = self.run.take() {
+                    let ids = run.failed.iter().map(|(entry_id, _)| *entry_id).collect();
+                    self.start(run.action, ids);
+                }
+                true
+            }
+            BatchMsg::Dismiss => {
+                self.run = None;
+                true
+            }
+            BatchMsg::Export => {
+                self.export();
+                false
+            }
+            BatchMsg::OpenTabs => {
+                self.open_tabs();
+                false
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        let count = self.props.entries.len();
+        let tags = || parse_tags(&self.tags);
+        let add = {
+            let tags = tags();
+            self.link.callback(move |_| BatchMsg::Start(BatchAction::AddTags(tags.clone())))
+        };
+        let remove = {
+            let tags = tags();
+            self.link.callback(move |_| BatchMsg::Start(BatchAction::RemoveTags(tags.clone())))
+        };
+        let busy = self.task.is_some();
+        let no_tags = tags().is_empty();
+        html! {
+            <div class="batch-bar shadow-sm rounded">
+                <div class="batch-actions">
+                    <span class="batch-count">{ format!("{} selected", count) }</span>
+                    <button class="batch-button" onclick=self.props.on_select_all.reform(|_| ())>{ "Select all" }</button>
+                    <button class="batch-button" onclick=self.props.on_clear.reform(|_| ())>{ "Clear" }</button>
+                    <span class="batch-separator"/>
+                    <button class="batch-button" disabled=busy || count == 0
+                        onclick=self.link.callback(|_| BatchMsg::Start(BatchAction::Completed(true)))>{ "Completed" }</button>
+                    <button class="batch-button" disabled=busy || count == 0
+                        onclick=self.link.callback(|_| BatchMsg::Start(BatchAction::Completed(false)))>{ "Not completed" }</button>
+                    <input type="text" class="batch-tags" placeholder="tags, comma separated" value=self.tags.clone()
+                        oninput=self.link.callback(|e: InputData| BatchMsg::TagsEdit(e.value))/>
+                    <button class="batch-button" disabled=busy || count == 0 || no_tags onclick=add>{ "Add tags" }</button>
+                    <button class="batch-button" disabled=busy || count == 0 || no_tags onclick=remove>{ "Remove tags" }</button>
+                    <span class="batch-separator"/>
+                    <button class="batch-button" disabled=count == 0
+                        onclick=self.link.callback(|_| BatchMsg::Export)>{ "Export" }</button>
+                    <button class="batch-button" disabled=count == 0
+                        onclick=self.link.callback(|_| BatchMsg::OpenTabs)>{ "Open in tabs" }</button>
+                </div>
+                { for self.run.iter().map(|run| self.view_run(run)) }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use crate::api::*;
+use crate::cards::ViewMode;
+use crate::sort;
+use crate::thumbnail::*;
+use url::Url;
+use yew::prelude::*;
+use yew::Properties;
+
+// titles longer than this (chars) are cut off on cards
+const TITLE_LENGTH: usize = 70;
+
+/// `text` with the case-insensitive occurrences of `terms` in `<mark>`s.
+pub fn highlighted(text: &str, terms: &[String]) -> Html {
+    let lower = text.to_lowercase();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for term in terms.iter().map(|t| t.to_lowercase()).filter(|t| !t.is_empty()) {
+        let mut from = 0;
+        while let Some(offset) = lower.get(from..).and_then(|rest| rest.find(&term)) {
+            let (start, end) = (from + offset, from + offset + term.len());
+            // lowercasing can move byte offsets in non-ascii text
+            if text.is_char_boundary(start) && text.is_char_boundary(end) {
+                ranges.push((start, end));
+            }
+            from = end;
+        }
+    }
+    ranges.sort_unstable();
+    let mut parts = Vec::new();
+    let mut pos = 0;
+    for (start, end) in ranges {
+        if end <= pos {
+            continue;
+        }
+        let start = start.max(pos);
+        if start > pos {
+            parts.push(html! { { &text[pos..start] } });
+        }
+        parts.push(html! { <mark>{ &text[start..end] }</mark> });
+        pos = end;
+    }
+    parts.push(html! { { &text[pos..] } });
+    html! { <>{ for parts }</> }
+}
+
+pub fn host_simplify(url: &str) -> String {
+    // TODO - don't hard code this
+    match url {
+        "export.arxiv.org" => "Arxiv".to_string(),
+        "www.arxiv.org" => "Arxiv".to_string(),
+        "www.github.com" => "Github".to_string(),
+        "github.com" => "Github".to_string(),
+        "medium.com" => "Medium".to_string(),
+        "www.reddit.com" => "Reddit".to_string(),
+        "twitter.com" => "Twitter".to_string(),
+        "www.youtube.com" => "YouTube".to_string(),
+        _ => url.to_string(),
+    }
+}
+
+pub enum CardMsg {
+    Hover(bool),
+}
+
+/// One gallery card. Everything it shows comes in as props, so hovering,
+/// selecting or completing an entry only re-renders that card.
+pub struct Card {
+    link: ComponentLink<Self>,
+    props: CardProps,
+    hover: bool,
+}
+
+#[derive(Properties, PartialEq, Clone)]
+pub struct CardProps {
+    pub entry: Cache,
+    pub thumbnail_file: String,
+    #[prop_or_default]
+    pub completed: bool,
+    /// the card last opened
+    #[prop_or_default]
+    pub clicked: bool,
+    #[prop_or_default]
+    pub cursor: bool,
+    #[prop_or_default]
+    pub selecting: bool,
+    #[prop_or_default]
+    pub selected: bool,
+    #[prop_or_default]
+    pub highlight: Vec<String>,
+    #[prop_or_default]
+    pub hit: Option<SearchHit>,
+    /// off when a day header above says it already
+    #[prop_or(true)]
+    pub show_date: bool,
+    /// (click, entry id)
+    pub on_click: Callback<(MouseEvent, i32)>,
+}
+
+impl Component for Card {
+    type Message = CardMsg;
+    type Properties = CardProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            link,
+            props,
+            hover: false,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            CardMsg::Hover(hover) => {
+                let changed = self.hover != hover;
+                self.hover = hover;
+                changed
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        let props = &self.props;
+        let item = &props.entry;
+        let img_style = "width: 60%;";
+
+        let img_class = if !self.hover {
+            "card-img-background shadow-sm bg-white rounded"
+        } else {
+            "card-img-foreground shadow-sm bg-white rounded"
+        };
+
+        let div_class = if !props.clicked {
+            if !self.hover {
+                "card shadow-sm p-3 mb-5 bg-white rounded"
+            } else {
+                "card-highlight card shadow p-3 mb-5 bg-white rounded"
+            }
+        } else {
+            "card shadow-none p-3 mb-5 bg-light rounded"
+        };
+        let div_class = marked_class(div_class, props.cursor, props.selected);
+
+        let entry_id = item.entry_id;
+        let on_click = props.on_click.reform(move |m| (m, entry_id));
+        let parsed = Url::parse(item.url.as_deref().unwrap_or(""));
+        let mut content = item.content.clone().unwrap_or_default();
+        if content.chars().count() > TITLE_LENGTH {
+            content = content.chars().take(TITLE_LENGTH).collect::<String>();
+            content.push_str("...");
+        }
+        let content = highlighted(&content, &props.highlight);
+        let hit = props.hit.as_ref();
+        html! {
+            <div id=format!("card-{}", entry_id) class=div_class
+                onmouseenter=self.link.callback(|_| CardMsg::Hover(true))
+                onmouseleave=self.link.callback(|_| CardMsg::Hover(false))
+                onclick=on_click>
+                {
+                    if props.selecting {
+                        html! { <input type="checkbox" class="card-select" checked=props.selected/> }
+                    } else {
+                        html! {}
+                    }
+                }
+                { if props.show_date { item.date.as_str() } else { "" } }
+                {
+                    if props.completed {
+                        html! { <span class="card-completed-badge" title="Completed">{ "✓ Done" }</span> }
+                    } else {
+                        html! {}
+                    }
+                }
+                <font color="grey">
+                {
+                    match &parsed {
+                        Ok(x) => { host_simplify(x.host_str().unwrap_or("")) }
+                        Err(_error) => { "".to_string() }
+                    }
+                }
+                </font>
+                {
+                    match hit {
+                        Some(hit) => html! {
+                            <span class="card-hit-source" title="Where the search matched">{ hit.source.label() }</span>
+                        },
+                        None => html! {},
+                    }
+                }
+                <hr/>
+                // <img src=thumbnail_file width="100%" style="height: 100px; overflow: hidden;"/>
+                <center>
+                    <Thumbnail src=props.thumbnail_file.clone() url=item.url.clone()
+                        style=img_style.to_string() class=img_class.to_string()/>
+                </center>
+                <center>
+                    {
+                        match item.url.as_ref() {
+                            Some(url) => html! {
+                                // following the link shouldn't also open the detail view
+                                <a href={ url.to_string() } onclick=Callback::from(|e: MouseEvent| e.stop_propagation())> { content } </a>
+                            },
+                            None => html! {
+                                { content }
+                            }
+                        }
+                    }
+                </center>
+                {
+                    match hit.and_then(|hit| hit.snippet.as_ref()) {
+                        Some(snippet) => html! {
+                            <div class="card-snippet">{ "…" }{ highlighted(snippet, &props.highlight) }{ "…" }</div>
+                        },
+                        None => html! {},
+                    }
+                }
+            </div>
+        }
+    }
+}
+
+/// `class` plus the keyboard cursor and selection markers.
+fn marked_class(class: &str, cursor: bool, selected: bool) -> String {
+    let mut class = class.to_string();
+    if cursor {
+        class.insert_str(0, "card-cursor ");
+    }
+    if selected {
+        class.insert_str(0, "card-selected ");
+    }
+    class
+}
+
+/// One entry of the table, list or grid layout, the denser siblings of
+/// `Card`. Like a card it only re-renders when its own props change.
+pub struct Row {
+    props: RowProps,
+}
+
+#[derive(Properties, PartialEq, Clone)]
+pub struct RowProps {
+    pub entry: Cache,
+    /// `Table`, `List` or `Grid`
+    pub view_mode: ViewMode,
+    /// grid thumbnail
+    #[prop_or_default]
+    pub thumbnail_file: String,
+    /// sorted, for the table
+    #[prop_or_default]
+    pub tags: Vec<String>,
+    #[prop_or_default]
+    pub completed: bool,
+    #[prop_or_default]
+    pub cursor: bool,
+    #[prop_or_default]
+    pub selecting: bool,
+    #[prop_or_default]
+    pub selected: bool,
+    #[prop_or_default]
+    pub highlight: Vec<String>,
+    /// (click, entry id)
+    pub on_click: Callback<(MouseEvent, i32)>,
+}
+
+impl Row {
+    fn view_select_box(&self) -> Html {
+        if self.props.selecting {
+            html! { <input type="checkbox" class="card-select" checked=self.props.selected/> }
+        } else {
+            html! {}
+        }
+    }
+
+    fn view_title(&self) -> Html {
+        let item = &self.props.entry;
+        let title = highlighted(item.content.as_deref().unwrap_or(""), &self.props.highlight);
+        match item.url.as_ref() {
+            Some(url) => html! {
+                <a href=url.to_string() onclick=Callback::from(|e: MouseEvent| e.stop_propagation())>{ title }</a>
+            },
+            None => title,
+        }
+    }
+
+    fn view_completed(&self) -> Html {
+        if self.props.completed {
+            html! { <span class="card-completed-badge" title="Completed">{ "✓ Done" }</span> }
+        } else {
+            html! {}
+        }
+    }
+}
+
+impl Component for Row {
+    type Message = ();
+    type Properties = RowProps;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Self { props }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        let props = &self.props;
+        let item = &props.entry;
+        let entry_id = item.entry_id;
+        let id = format!("card-{}", entry_id);
+        let onclick = props.on_click.reform(move |m| (m, entry_id));
+        let class = |class| marked_class(class, props.cursor, props.selected);
+        match props.view_mode {
+            ViewMode::Table => html! {
+                <tr id=id class=class("table-row") onclick=onclick>
+                    <td class="table-date">{ self.view_select_box() }{ &item.date }</td>
+                    <td class="table-domain">{ sort::domain(item).unwrap_or_default() }</td>
+                    <td class="table-title">{ self.view_title() }</td>
+                    <td class="table-tags">
+                        { for props.tags.iter().map(|tag| html! { <span class="table-tag">{ tag }</span> }) }
+                    </td>
+                    <td>{ if props.completed { "✓" } else { "" } }</td>
+                </tr>
+            },
+            ViewMode::List => html! {
+                <li id=id class=class("list-item") onclick=onclick>
+                    { self.view_select_box() }
+                    <span class="list-date">{ &item.date }</span>
+                    <span class="list-domain">{ sort::domain(item).unwrap_or_default() }</span>
+                    <span class="list-title">{ self.view_title() }</span>
+                    { self.view_completed() }
+                </li>
+            },
+            ViewMode::Grid | ViewMode::Cards => html! {
+                <div id=id class=class("grid-item shadow-sm rounded") onclick=onclick>
+                    <Thumbnail src=props.thumbnail_file.clone() url=item.url.clone() class="grid-thumbnail"/>
+                    <div class="grid-caption">
+                        { self.view_select_box() }
+                        { self.view_completed() }
+                        { self.view_title() }
+                    </div>
+                </div>
+            },
+        }
+    }
+}
@@ -1,140 +1,483 @@
 use crate::api::*;
-use std::collections::HashMap;
-use url::*;
+use crate::card::*;
+use crate::sort::{GallerySort, SortKey};
+use chrono::{Datelike, NaiveDate};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
 use yew::prelude::*;
+use web_sys::{Element, ScrollIntoViewOptions, ScrollLogicalPosition};
+use yew::utils::{document, window};
 use crate::app_router::*;
+use yew_router::agent::RouteRequest;
 use yew_router::prelude::*;
 
-pub type Link = RouterAnchor<AppRoute>;
-
 #[derive(Debug)]
 pub enum CardsMsg {
-    CardMouseOver(MouseEvent, i32),
-    CardClick(MouseEvent, i32, Cache),
+    CardClick(MouseEvent, i32),
+    ToggleGroup(String),
+    Scroll,
+    // blocks near the viewport changed
+    Window,
+    LoadMore,
+}
+
+// start loading the next page this far (px) above the bottom of the page
+const SCROLL_MARGIN: f64 = 600.0;
+
+// blocks closer than this (px) to the viewport are mounted, the rest are
+// spacers of their last measured height
+const WINDOW_MARGIN: f64 = 1200.0;
+// rows of entries mounted or unmounted together
+const BLOCK_ROWS: usize = 5;
+const BLOCK_ROWS_DENSE: usize = 50;
+const CARD_COLUMNS: usize = 4;
+// grid gap (px), see `.cards-grid`
+const GRID_GAP: f64 = 10.0;
+
+// thumbnail widths (px) the grid size slider goes between
+pub const GRID_SIZE_MIN: u32 = 120;
+pub const GRID_SIZE_MAX: u32 = 480;
+pub const GRID_SIZE_DEFAULT: u32 = 240;
+
+/// How the gallery lays out its entries.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ViewMode {
+    #[default]
+    Cards,
+    Table,
+    List,
+    Grid,
+}
+
+impl ViewMode {
+    pub const ALL: [ViewMode; 4] = [ViewMode::Cards, ViewMode::Table, ViewMode::List, ViewMode::Grid];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ViewMode::Cards => "Cards",
+            ViewMode::Table => "Table",
+            ViewMode::List => "List",
+            ViewMode::Grid => "Grid",
+        }
+    }
+
+    pub fn as_param(&self) -> &'static str {
+        match self {
+            ViewMode::Cards => "cards",
+            ViewMode::Table => "table",
+            ViewMode::List => "list",
+            ViewMode::Grid => "grid",
+        }
+    }
+
+    pub fn from_param(param: &str) -> Option<Self> {
+        ViewMode::ALL.iter().copied().find(|mode| mode.as_param() == param)
+    }
+}
+
+/// Date buckets the gallery can be grouped into, by `Cache.date`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum GroupBy {
+    #[default]
+    None,
+    Day,
+    Week,
+    Month,
+}
+
+impl GroupBy {
+    pub const ALL: [GroupBy; 4] = [GroupBy::None, GroupBy::Day, GroupBy::Week, GroupBy::Month];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GroupBy::None => "Ungrouped",
+            GroupBy::Day => "By day",
+            GroupBy::Week => "By week",
+            GroupBy::Month => "By month",
+        }
+    }
+
+    pub fn as_param(&self) -> &'static str {
+        match self {
+            GroupBy::None => "none",
+            GroupBy::Day => "day",
+            GroupBy::Week => "week",
+            GroupBy::Month => "month",
+        }
+    }
+
+    pub fn from_param(param: &str) -> Option<Self> {
+        GroupBy::ALL.iter().copied().find(|group_by| group_by.as_param() == param)
+    }
+
+    /// (bucket key, header label) of an entry, `None` when not grouping.
+    fn bucket(&self, item: &Cache) -> Option<(String, String)> {
+        let day = NaiveDate::parse_from_str(&item.date, "%Y-%m-%d").ok();
+        let bucket = match (self, day) {
+            (GroupBy::None, _) => return None,
+            (_, None) => ("undated".to_string(), "Undated".to_string()),
+            (GroupBy::Day, Some(day)) => (day.to_string(), day.format("%A, %-d %B %Y").to_string()),
+            (GroupBy::Week, Some(day)) => {
+                let week = day.iso_week();
+                let monday = NaiveDate::from_isoywd_opt(week.year(), week.week(), chrono::Weekday::Mon).unwrap_or(day);
+                (
+                    format!("{}-W{:02}", week.year(), week.week()),
+                    format!("Week {}, {} (from {})", week.week(), week.year(), monday.format("%-d %B")),
+                )
+            }
+            (GroupBy::Month, Some(day)) => (day.format("%Y-%m").to_string(), day.format("%B %Y").to_string()),
+        };
+        Some(bucket)
+    }
+}
+
+/// A run of consecutive entries in the same date bucket, split into blocks
+/// for the windowing. Block indexes count across all groups, collapsed ones
+/// included, so collapsing doesn't shift the measured heights.
+struct Group {
+    // empty when not grouping
+    key: String,
+    label: String,
+    entries: Range<usize>,
+    // (block index, entries)
+    blocks: Vec<(usize, Range<usize>)>,
+}
+
+fn near_bottom() -> bool {
+    let window = window();
+    let viewport = window.inner_height().ok().and_then(|h| h.as_f64()).unwrap_or(0.0);
+    let scrolled = window.scroll_y().unwrap_or(0.0);
+    let height = document()
+        .document_element()
+        .map(|e| e.scroll_height() as f64)
+        .unwrap_or(0.0);
+    scrolled + viewport + SCROLL_MARGIN >= height
 }
 
 #[derive(Debug)]
 pub struct Cards {
     pub link: ComponentLink<Self>,
     pub entries: Option<Vec<Cache>>,
-    pub entry_id_mouseover: Option<i32>,
+    pub completed: HashSet<i32>,
     pub entry_id_click: Option<i32>,
     pub card_click_callback: Callback<Option<Cache>>,
+    pub has_more: bool,
+    pub loading_more: bool,
+    pub load_more_callback: Callback<()>,
+    pub highlight: Vec<String>,
+    pub hits: HashMap<i32, SearchHit>,
+    pub cursor: Option<i32>,
+    pub selecting: bool,
+    pub selected: HashSet<i32>,
+    pub select_callback: Callback<(i32, bool)>,
+    pub view_mode: ViewMode,
+    pub grid_size: u32,
+    pub entry_tags: HashMap<i32, HashSet<String>>,
+    pub sort: GallerySort,
+    pub sort_callback: Callback<GallerySort>,
+    pub group_by: GroupBy,
+    // keys of the groups folded away
+    collapsed: HashSet<String>,
+    // cursor last scrolled into view
+    scrolled_to: Option<i32>,
+    // handed to every `Card`, a new callback per render would re-render them all
+    card_click: Callback<(MouseEvent, i32)>,
+    // indexes of the blocks rendered in full
+    mounted: HashSet<usize>,
+    // measured block heights for the spacers, valid for `layout`
+    heights: HashMap<usize, f64>,
+    layout: (ViewMode, usize, GroupBy),
+    // width of the cards column, for the grid columns
+    width: f64,
+    container: NodeRef,
+    api: ApiClient,
+    router: RouteAgentDispatcher<()>,
+    // window scroll and resize listener, removed again in `destroy`
+    on_scroll: Closure<dyn Fn()>,
 }
 
 #[derive(Clone, Properties)]
 pub struct Props {
     pub entries: Option<Vec<Cache>>,
+    /// entries that get a completed badge
+    #[prop_or_default]
+    pub completed: HashSet<i32>,
     pub card_click_callback: Callback<Option<Cache>>,
+    /// older entries can be fetched with `load_more_callback`
+    #[prop_or_default]
+    pub has_more: bool,
+    #[prop_or_default]
+    pub loading_more: bool,
+    #[prop_or_default]
+    pub load_more_callback: Callback<()>,
+    /// search terms marked in titles and snippets
+    #[prop_or_default]
+    pub highlight: Vec<String>,
+    /// where the current search matched each entry
+    #[prop_or_default]
+    pub hits: HashMap<i32, SearchHit>,
+    /// card picked with the keyboard, kept in view
+    #[prop_or_default]
+    pub cursor: Option<i32>,
+    /// clicks select cards instead of opening them
+    #[prop_or_default]
+    pub selecting: bool,
+    #[prop_or_default]
+    pub selected: HashSet<i32>,
+    /// (entry id, shift held) for a click while selecting or a Shift-click
+    #[prop_or_default]
+    pub select_callback: Callback<(i32, bool)>,
+    #[prop_or_default]
+    pub view_mode: ViewMode,
+    /// thumbnail width in the grid view
+    #[prop_or(GRID_SIZE_DEFAULT)]
+    pub grid_size: u32,
+    /// tags of each entry, for the table view
+    #[prop_or_default]
+    pub entry_tags: HashMap<i32, HashSet<String>>,
+    /// the table headers show and change the gallery sort
+    #[prop_or_default]
+    pub sort: GallerySort,
+    #[prop_or_default]
+    pub sort_callback: Callback<GallerySort>,
+    /// date buckets with sticky, collapsible headers
+    #[prop_or_default]
+    pub group_by: GroupBy,
 }
 
-fn host_simplify(url: &str) -> String {
-    // TODO - don't hard code this
-    match url {
-        "export.arxiv.org" => "Arxiv".to_string(),
-        "www.arxiv.org" => "Arxiv".to_string(),
-        "www.github.com" => "Github".to_string(),
-        "github.com" => "Github".to_string(),
-        "medium.com" => "Medium".to_string(),
-        "www.reddit.com" => "Reddit".to_string(),
-        "twitter.com" => "Twitter".to_string(),
-        "www.youtube.com" => "YouTube".to_string(),
-        _ => url.to_string(),
-    }
+fn block_id(index: usize) -> String {
+    format!("cards-block-{}", index)
 }
 
 impl Cards {
-    fn view_card(
-        &self,
-        parsed: &Result<Url, url::ParseError>,
-        thumbnail_file: &String,
-        item: &Cache,
-    ) -> Html {
-        let img_style = "width: 60%;";
-        let item_clone = item.clone();
-
-        let img_class = if item.entry_id != self.entry_id_mouseover.unwrap_or(-1) {
-            "card-img-background shadow-sm bg-white rounded"
-        } else {
-            "card-img-foreground shadow-sm bg-white rounded"
+    fn view_sort_header(&self, key: SortKey) -> Html {
+        let arrow = match (self.sort.key == key, self.sort.dir) {
+            (false, _) => "",
+            (true, SortDir::Fwd) => " ▲",
+            (true, SortDir::Rev) => " ▼",
         };
+        let next = self.sort.toggle(key);
+        let sort_callback = self.sort_callback.clone();
+        html! {
+            <th class="sortable" onclick=Callback::from(move |_| sort_callback.emit(next))>
+                { format!("{}{}", key.label(), arrow) }
+            </th>
+        }
+    }
 
-        let div_class = if item.entry_id != self.entry_id_click.unwrap_or(-1) {
-            if item.entry_id != self.entry_id_mouseover.unwrap_or(-1) {
-                "card shadow-sm p-3 mb-5 bg-white rounded"
-            } else {
-                "card-highlight card shadow p-3 mb-5 bg-white rounded"
-            }
-        } else {
-            "card shadow-none p-3 mb-5 bg-light rounded"
+    fn view_table_header(&self) -> Html {
+        html! {
+            <thead>
+                <tr>
+                    { self.view_sort_header(SortKey::Date) }
+                    { self.view_sort_header(SortKey::Domain) }
+                    { self.view_sort_header(SortKey::Title) }
+                    <th>{ "Tags" }</th>
+                    <th>{ "Done" }</th>
+                </tr>
+            </thead>
+        }
+    }
+
+    fn view_row(&self, item: &Cache) -> Html {
+        let entry_id = item.entry_id;
+        let mut tags: Vec<String> = match self.view_mode {
+            ViewMode::Table => self.entry_tags.get(&entry_id).into_iter().flatten().cloned().collect(),
+            _ => Vec::new(),
         };
+        tags.sort();
+        html! {
+            <Row key=entry_id.to_string() entry=item.clone() view_mode=self.view_mode
+                thumbnail_file=self.thumbnail_file(item) tags=tags
+                completed=self.completed.contains(&entry_id) cursor=self.cursor == Some(entry_id)
+                selecting=self.selecting selected=self.selected.contains(&entry_id)
+                highlight=self.highlight.clone() on_click=self.card_click.clone()/>
+        }
+    }
+
+    fn view_card(&self, item: &Cache) -> Html {
+        let entry_id = item.entry_id;
+        html! {
+            <Card key=entry_id.to_string() entry=item.clone() thumbnail_file=self.thumbnail_file(item)
+                completed=self.completed.contains(&entry_id) clicked=self.entry_id_click == Some(entry_id)
+                cursor=self.cursor == Some(entry_id) selecting=self.selecting show_date=self.group_by != GroupBy::Day
+                selected=self.selected.contains(&entry_id) highlight=self.highlight.clone()
+                hit=self.hits.get(&entry_id).cloned() on_click=self.card_click.clone()/>
+        }
+    }
+
+    fn thumbnail_file(&self, item: &Cache) -> String {
+        item.thumbnail_file
+            .as_ref()
+            .map(|file| self.api.frontend_file_url(file))
+            .unwrap_or_default()
+    }
 
-        let callback_mouseover = |entry_id| {
-            self.link
-                .callback(move |m| CardsMsg::CardMouseOver(m, entry_id))
+    fn columns(&self) -> usize {
+        match self.view_mode {
+            ViewMode::Cards => CARD_COLUMNS,
+            ViewMode::Grid => ((self.width + GRID_GAP) / (self.grid_size as f64 + GRID_GAP)).max(1.0) as usize,
+            ViewMode::Table | ViewMode::List => 1,
+        }
+    }
+
+    fn block_size(&self) -> usize {
+        let rows = match self.view_mode {
+            ViewMode::Cards | ViewMode::Grid => BLOCK_ROWS,
+            ViewMode::Table | ViewMode::List => BLOCK_ROWS_DENSE,
         };
+        rows * self.columns()
+    }
 
-        let callback_click = |entry_id| {
-            self.link
-                .callback(move |m| CardsMsg::CardClick(m, entry_id, item_clone.clone()))
+    /// Height for an unmounted block: as measured, else the average of the
+    /// measured ones, else a guess.
+    fn spacer_height(&self, index: usize) -> f64 {
+        if let Some(height) = self.heights.get(&index) {
+            return *height;
+        }
+        if !self.heights.is_empty() {
+            return self.heights.values().sum::<f64>() / self.heights.len() as f64;
+        }
+        let row = match self.view_mode {
+            ViewMode::Cards => 400.0,
+            ViewMode::Grid => self.grid_size as f64 * 0.75 + 40.0,
+            ViewMode::Table | ViewMode::List => 30.0,
         };
-        // log::info!("thumbnail file is {:?}", &thumbnail_file);
-        let mut content = item.content.clone().unwrap_or("".to_owned());
-        let max_length = 70;
-        if content.len() > max_length {
-            content = content.chars().take(max_length).collect::<String>();
-            content.push_str("...");
+        row * (self.block_size() / self.columns()) as f64
+    }
+
+    fn view_block(&self, index: usize, entries: &[Cache]) -> Html {
+        let id = block_id(index);
+        match self.view_mode {
+            ViewMode::Cards => html! {
+                <div id=id class="cards">{ for entries.iter().map(|item| self.view_card(item)) }</div>
+            },
+            ViewMode::Grid => html! {
+                <div id=id class="cards-grid" style=format!("grid-template-columns: repeat({}, 1fr);", self.columns())>
+                    { for entries.iter().map(|item| self.view_row(item)) }
+                </div>
+            },
+            ViewMode::List => html! {
+                <ul id=id class="gallery-list">{ for entries.iter().map(|item| self.view_row(item)) }</ul>
+            },
+            ViewMode::Table => html! {
+                <tbody id=id>{ for entries.iter().map(|item| self.view_row(item)) }</tbody>
+            },
         }
+    }
+
+    fn view_spacer(&self, index: usize) -> Html {
+        let style = format!("height: {}px;", self.spacer_height(index));
+        match self.view_mode {
+            ViewMode::Table => html! {
+                <tbody id=block_id(index)><tr><td colspan="5" style=style/></tr></tbody>
+            },
+            _ => html! { <div id=block_id(index) style=style/> },
+        }
+    }
+
+    fn groups(&self) -> Vec<Group> {
+        let entries = match &self.entries {
+            Some(entries) => entries,
+            None => return Vec::new(),
+        };
+        let mut groups: Vec<Group> = Vec::new();
+        for (index, item) in entries.iter().enumerate() {
+            let (key, label) = self.group_by.bucket(item).unwrap_or_default();
+            match groups.last_mut() {
+                Some(group) if group.key == key => group.entries.end = index + 1,
+                _ => groups.push(Group {
+                    key,
+                    label,
+                    entries: index..index + 1,
+                    blocks: Vec::new(),
+                }),
+            }
+        }
+        let block_size = self.block_size();
+        let mut next = 0;
+        for group in &mut groups {
+            let mut start = group.entries.start;
+            while start < group.entries.end {
+                let end = (start + block_size).min(group.entries.end);
+                group.blocks.push((next, start..end));
+                next += 1;
+                start = end;
+            }
+        }
+        groups
+    }
+
+    fn view_group_header(&self, group: &Group, entries: &[Cache]) -> Html {
+        if group.key.is_empty() {
+            return html! {};
+        }
+        let collapsed = self.collapsed.contains(&group.key);
+        let count = group.entries.len();
+        let completed = entries[group.entries.clone()]
+            .iter()
+            .filter(|item| self.completed.contains(&item.entry_id))
+            .count();
+        let key = group.key.clone();
+        let onclick = self.link.callback(move |_| CardsMsg::ToggleGroup(key.clone()));
+        let header = html! {
+            <>
+                <span class="group-toggle">{ if collapsed { "▸" } else { "▾" } }</span>
+                <span class="group-label">{ &group.label }</span>
+                <span class="group-count">
+                    { format!("{} {}", count, if count == 1 { "entry" } else { "entries" }) }
+                </span>
+                <span class="group-completed" title="Completed">
+                    { format!("{}/{} done", completed, count) }
+                    <progress value=completed.to_string() max=count.to_string()/>
+                </span>
+            </>
+        };
+        match self.view_mode {
+            ViewMode::Table => html! {
+                <tbody class="group-header">
+                    <tr onclick=onclick><th colspan="5">{ header }</th></tr>
+                </tbody>
+            },
+            _ => html! { <div class="group-header" onclick=onclick>{ header }</div> },
+        }
+    }
+
+    fn view_group(&self, group: &Group, entries: &[Cache]) -> Html {
+        let blocks = if self.collapsed.contains(&group.key) {
+            &[][..]
+        } else {
+            &group.blocks[..]
+        };
         html! {
-            <div class={ div_class } onmouseover=callback_mouseover(item.entry_id) onclick = callback_click(item.entry_id)>
-                { &item.date }
-                <font color="grey">
-                {
-                    match &parsed {
-                        Ok(x) => { host_simplify(x.host_str().unwrap()) }
-                        Err(error) => { "".to_string() }
-                    }
-                }
-                </font>
-                <hr/>
-                // <img src=thumbnail_file width="100%" style="height: 100px; overflow: hidden;"/>
-                <center>
-
-                    <Link route=AppRoute::Detail>
-                    <img src=thumbnail_file.to_string() style=img_style class=img_class/>
-                    </Link>
-                </center>
-                <center>
-                    {
-                        match item.url.as_ref() {
-                            Some(url) => html! {
-                                <a href={ url.to_string() }> { content } </a>
-                            },
-                            None => html! {
-                                { content }
-                            }
-                        }
+            <>
+                { self.view_group_header(group, entries) }
+                { for blocks.iter().map(|(index, range)| {
+                    if self.mounted.contains(index) {
+                        self.view_block(*index, &entries[range.clone()])
+                    } else {
+                        self.view_spacer(*index)
                     }
-                </center>
-            </div>
+                }) }
+            </>
         }
     }
 
     fn view_entries(&self) -> Html {
-        let blank = String::from("");
         match self.entries {
             Some(ref entries) => {
-                log::info!("{:#?} results fetched.", entries.len());
-
-                html! {
-                    {
-                        for entries.iter().map(|item| {
-                            // log::info!("{:#?} : item.", item);
-                            let parsed = Url::parse(item.url.as_ref().unwrap_or(&"".to_owned()));
-                            let thumbnail_file = item.thumbnail_file.as_ref().unwrap_or(&blank);
-                            self.view_card(&parsed, &thumbnail_file, &item)
-                        })
-                    }
+                let groups = self.groups();
+                let groups = groups.iter().map(|group| self.view_group(group, entries));
+                match self.view_mode {
+                    ViewMode::Table => html! {
+                        <table class="gallery-table">
+                            { self.view_table_header() }
+                            { for groups }
+                        </table>
+                    },
+                    _ => html! { { for groups } },
                 }
             }
             None => {
@@ -142,6 +485,121 @@ impl Cards {
             }
         }
     }
+
+    /// Remeasure the column width, `true` if the layout changed. Block
+    /// heights measured for another layout are dropped.
+    fn relayout(&mut self) -> bool {
+        if let Some(container) = self.container.cast::<Element>() {
+            self.width = container.client_width() as f64;
+        }
+        let layout = (self.view_mode, self.columns(), self.group_by);
+        if layout == self.layout {
+            return false;
+        }
+        self.layout = layout;
+        self.heights.clear();
+        true
+    }
+
+    /// Measure the mounted blocks and mount the ones near the viewport,
+    /// `true` if that changed which are mounted.
+    fn update_window(&mut self) -> bool {
+        for index in &self.mounted {
+            if let Some(block) = document().get_element_by_id(&block_id(*index)) {
+                self.heights.insert(*index, block.get_bounding_client_rect().height());
+            }
+        }
+        let blocks: Vec<usize> = self
+            .groups()
+            .iter()
+            .filter(|group| !self.collapsed.contains(&group.key))
+            .flat_map(|group| group.blocks.iter().map(|(index, _)| *index))
+            .collect();
+        let viewport = window().inner_height().ok().and_then(|h| h.as_f64()).unwrap_or(0.0);
+        let near: HashSet<usize> = blocks
+            .into_iter()
+            .filter(|index| match document().get_element_by_id(&block_id(*index)) {
+                Some(block) => {
+                    let rect = block.get_bounding_client_rect();
+                    rect.bottom() > -WINDOW_MARGIN && rect.top() < viewport + WINDOW_MARGIN
+                }
+                // not rendered yet
+                None => *index == 0,
+            })
+            .collect();
+        if near == self.mounted {
+            return false;
+        }
+        self.mounted = near;
+        true
+    }
+
+    /// Keep the keyboard cursor in view. An unmounted card's block is
+    /// scrolled to and mounted first, the card follows on the next render.
+    fn scroll_to_cursor(&mut self) {
+        if self.cursor == self.scrolled_to {
+            return;
+        }
+        let position = self.cursor.and_then(|id| {
+            self.entries.as_ref()?.iter().position(|e| e.entry_id == id)
+        });
+        let (entry_id, position) = match (self.cursor, position) {
+            (Some(entry_id), Some(position)) => (entry_id, position),
+            _ => {
+                self.scrolled_to = self.cursor;
+                return;
+            }
+        };
+        let options = ScrollIntoViewOptions::new();
+        options.set_block(ScrollLogicalPosition::Nearest);
+        match document().get_element_by_id(&format!("card-{}", entry_id)) {
+            Some(card) => {
+                card.scroll_into_view_with_scroll_into_view_options(&options);
+                self.scrolled_to = self.cursor;
+            }
+            None => {
+                let groups = self.groups();
+                let (key, index) = match groups.iter().find(|group| group.entries.contains(&position)) {
+                    Some(group) => {
+                        let block = group.blocks.iter().find(|(_, range)| range.contains(&position));
+                        (group.key.clone(), block.map(|(index, _)| *index).unwrap_or_default())
+                    }
+                    None => return,
+                };
+                // the cursor moved into a folded group
+                self.collapsed.remove(&key);
+                if let Some(spacer) = document().get_element_by_id(&block_id(index)) {
+                    spacer.scroll_into_view_with_scroll_into_view_options(&options);
+                }
+                self.mounted.insert(index);
+                self.link.send_message(CardsMsg::Window);
+            }
+        }
+    }
+
+    fn view_footer(&self) -> Html {
+        let count = match &self.entries {
+            Some(entries) if !entries.is_empty() => entries.len(),
+            _ => return html! {},
+        };
+        let footer = if self.loading_more {
+            html! { {"Loading more entries..."} }
+        } else if self.has_more {
+            html! {
+                <button class="sort-button shadow-sm p-2 bg-white rounded"
+                    onclick=self.link.callback(|_| CardsMsg::LoadMore)>{ "Load more" }</button>
+            }
+        } else {
+            html! { { format!("End of results - {} entries", count) } }
+        };
+        html! { <div class="cards-footer">{ footer }</div> }
+    }
+
+    fn request_more(&self) {
+        if self.has_more && !self.loading_more && near_bottom() {
+            self.load_more_callback.emit(());
+        }
+    }
 }
 
 impl Component for Cards {
@@ -150,48 +608,159 @@ impl Component for Cards {
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         log::info!("Creating cards component");
+        let scroll = link.callback(|_| CardsMsg::Scroll);
+        let on_scroll = Closure::wrap(Box::new(move || scroll.emit(())) as Box<dyn Fn()>);
+        for event in ["scroll", "resize"] {
+            if let Err(e) = window().add_event_listener_with_callback(event, on_scroll.as_ref().unchecked_ref()) {
+                log::info!("could not listen to {} events: {:?}", event, e);
+            }
+        }
+        let card_click = link.callback(|(m, entry_id)| CardsMsg::CardClick(m, entry_id));
         Self {
-            link: link,
+            link,
             entries: props.entries,
-            entry_id_mouseover: None,
+            completed: props.completed,
             entry_id_click: None,
             card_click_callback: props.card_click_callback,
+            has_more: props.has_more,
+            loading_more: props.loading_more,
+            load_more_callback: props.load_more_callback,
+            highlight: props.highlight,
+            hits: props.hits,
+            cursor: props.cursor,
+            selecting: props.selecting,
+            selected: props.selected,
+            select_callback: props.select_callback,
+            view_mode: props.view_mode,
+            grid_size: props.grid_size,
+            entry_tags: props.entry_tags,
+            sort: props.sort,
+            sort_callback: props.sort_callback,
+            group_by: props.group_by,
+            collapsed: HashSet::new(),
+            scrolled_to: None,
+            card_click,
+            mounted: (0..2).collect(),
+            heights: HashMap::new(),
+            layout: (props.view_mode, 0, props.group_by),
+            width: 0.0,
+            container: NodeRef::default(),
+            api: ApiClient::new(),
+            router: RouteAgentDispatcher::new(),
+            on_scroll,
         }
     }
 
     fn change(&mut self, props: Self::Properties) -> bool {
+        // the parent makes new callbacks on every render, they don't count
+        let changed = self.entries != props.entries
+            || self.completed != props.completed
+            || self.has_more != props.has_more
+            || self.loading_more != props.loading_more
+            || self.highlight != props.highlight
+            || self.hits != props.hits
+            || self.cursor != props.cursor
+            || self.selecting != props.selecting
+            || self.selected != props.selected
+            || self.view_mode != props.view_mode
+            || self.grid_size != props.grid_size
+            || self.entry_tags != props.entry_tags
+            || self.sort != props.sort
+            || self.group_by != props.group_by;
+        if self.entries != props.entries {
+            let old = self.entries.as_deref().unwrap_or_default();
+            let appended = props.entries.as_deref().is_some_and(|new| new.starts_with(old));
+            if appended {
+                // a page was loaded: earlier blocks keep their entries, only
+                // the last one may have grown
+                if let Some(last) = self.heights.keys().max().copied() {
+                    self.heights.remove(&last);
+                }
+            } else {
+                // heights of the blocks that went out of view are stale now
+                self.heights.clear();
+            }
+        }
         self.entries = props.entries;
+        self.completed = props.completed;
         self.card_click_callback = props.card_click_callback;
-
-        true
+        self.has_more = props.has_more;
+        self.loading_more = props.loading_more;
+        self.load_more_callback = props.load_more_callback;
+        self.highlight = props.highlight;
+        self.hits = props.hits;
+        self.cursor = props.cursor;
+        self.selecting = props.selecting;
+        self.selected = props.selected;
+        self.select_callback = props.select_callback;
+        self.view_mode = props.view_mode;
+        self.grid_size = props.grid_size;
+        self.entry_tags = props.entry_tags;
+        self.sort = props.sort;
+        self.sort_callback = props.sort_callback;
+        self.group_by = props.group_by;
+        self.relayout() || changed
     }
 
     fn update(&mut self, msg: Self::Message) -> bool {
         use CardsMsg::*;
-        log::info!("update");
         match msg {
-            CardMouseOver(_m, entry_id) => {
-                self.entry_id_mouseover = Some(entry_id);
+            CardClick(m, entry_id) if self.selecting || m.shift_key() => {
+                self.select_callback.emit((entry_id, m.shift_key()));
+                false
+            }
+            CardClick(_m, entry_id) => {
+                self.entry_id_click = Some(entry_id);
+                let item = self.entries.iter().flatten().find(|e| e.entry_id == entry_id).cloned();
+                self.card_click_callback.emit(item);
+                let route = Route::from(AppRoute::Detail(entry_id));
+                self.router.send(RouteRequest::ChangeRoute(route));
                 true
             }
-            CardClick(_m, entry_id, item) => {
-                // TODO
-                if self.entry_id_click.unwrap_or(-1) != entry_id {
-                    self.entry_id_click = Some(entry_id);
-                    self.card_click_callback.emit(Some(item));
-                } else {
-                    self.entry_id_click = None;
-                    self.card_click_callback.emit(None);
+            ToggleGroup(key) => {
+                if !self.collapsed.remove(&key) {
+                    self.collapsed.insert(key);
                 }
                 true
             }
+            Scroll => {
+                self.request_more();
+                let relayout = self.relayout();
+                self.update_window() || relayout
+            }
+            Window => true,
+            LoadMore => {
+                self.load_more_callback.emit(());
+                false
+            }
+        }
+    }
+
+    fn rendered(&mut self, _first_render: bool) {
+        // a first page shorter than the window never scrolls
+        self.request_more();
+        let relayout = self.relayout();
+        if self.update_window() || relayout {
+            self.link.send_message(CardsMsg::Window);
+        }
+        self.scroll_to_cursor();
+    }
+
+    fn destroy(&mut self) {
+        for event in ["scroll", "resize"] {
+            let _ = window().remove_event_listener_with_callback(event, self.on_scroll.as_ref().unchecked_ref());
         }
     }
 
     fn view(&self) -> Html {
+        let class = match self.view_mode {
+            ViewMode::Cards | ViewMode::Grid => "cards-virtual",
+            ViewMode::Table | ViewMode::List => "cards-virtual cards-rows",
+        };
         html! {
-              <div class="cards">
+              <div class=class ref=self.container.clone()>
                   { self.view_entries() }
+                  { self.view_footer() }
               </div>
         }
     }
@@ -1,41 +1,50 @@
-use serde::{Deserialize, Serialize};
-use serde_json::json;
 use crate::api::*;
-use yew::services::fetch::{FetchService, FetchTask, Request, Response};
-use yew::{
-    format::{Json, Nothing},
-    prelude::*,
-    utils::*,
-};
+use crate::keymap::*;
+use crate::notifications::*;
+use crate::offline::*;
+use crate::tags::prompt_tags;
+use yew::agent::{Bridge, Bridged, Dispatched, Dispatcher};
+use yew::prelude::*;
 use yew::Properties;
-use wasm_bindgen::prelude::*;
 use crate::external::*;
 // use wasm_bindgen::JsCast;
 
-#[derive(Deserialize, Debug, Clone)]
-pub struct CompletedResponse {
-    pub code: i64,
-}
-
 pub enum DetailMsg {
+    GetEntry,
+    ReceiveEntry(ApiResult<Option<Cache>>),
     CompletedChange(ChangeData),
-    CompletedResponse(Result<Vec<CompletedResponse>, anyhow::Error>),
+    SubmitCompleted(bool),
+    CompletedResponse(CompletedPayload, ApiResult<i64>),
     GetCompleted,
-    ReceiveCompleted(Result<Vec<bool>, anyhow::Error>),
+    ReceiveCompleted(ApiResult<bool>),
+    Key(Action),
+    TagsResponse(TagsPayload, ApiResult<i64>),
 }
 
 pub struct Detail {
     pub link: ComponentLink<Self>,
+    pub entry_id: i32,
     pub entry: Option<Cache>,
-    pub ace_editor: Option<JsValue>,
     pub completed: bool,
-    submit_task: Option<FetchTask>,
+    api: ApiClient,
+    entry_task: Option<ApiTask>,
+    // one slot per kind of request, so none cancels another
+    get_completed_task: Option<ApiTask>,
+    completed_task: Option<ApiTask>,
+    tags_task: Option<ApiTask>,
+    notifier: Dispatcher<NotificationBus>,
+    offline: Dispatcher<Offline>,
+    _keys: Box<dyn Bridge<KeyBindings>>,
     // TODO: get ace callback working
     // pub ace_callback: dyn Fn(JsValue) -> (),
 }
 
 #[derive(Properties, Clone)]
 pub struct Props {
+    pub entry_id: i32,
+    /// the entry if the caller already has it (e.g. from a card click),
+    /// otherwise it is loaded by id
+    #[prop_or_default]
     pub entry: Option<Cache>,
 }
 
@@ -45,7 +54,7 @@ fn youtube_url(url: String) -> String {
     // todo - 0..11 only works for ascii byte sized chars- make this more general
     let video_id = tokens[1].to_owned()[0..11].to_string(); 
     log::info!("{:?}", video_id);
-    return format!("http://www.youtube.com/embed/{}", video_id);
+    format!("http://www.youtube.com/embed/{}", video_id)
 }
 
 fn iframeify_url(url: String) -> (String, String) {
@@ -86,68 +95,85 @@ impl Component for Detail {
     type Properties = Props;
     // let callback = |buffer: JsValue| log::info!("ace callback");
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let cb = link.callback_once(|_: String| DetailMsg::GetCompleted);
-        cb.emit("".to_string()); // TODO - what's the right way to handle a message without parameters
-        log::info!("sent GetCompleted message");
+        let entry_id = props.entry_id;
+        let entry = props.entry.filter(|e| e.entry_id == entry_id);
+        if entry.is_some() {
+            link.send_message(DetailMsg::GetCompleted);
+        } else {
+            link.send_message(DetailMsg::GetEntry);
+        }
+        let keys = KeyBindings::bridge(link.callback(DetailMsg::Key));
         Self {
-            link: link,
-            entry: props.entry,
-            ace_editor: None,
+            link,
+            entry_id,
+            entry,
             completed: false,
-            submit_task: None
+            api: ApiClient::new(),
+            entry_task: None,
+            get_completed_task: None,
+            completed_task: None,
+            tags_task: None,
+            notifier: NotificationBus::dispatcher(),
+            offline: Offline::dispatcher(),
+            _keys: keys,
             // ace_callback: unimplemented!(),
         }
     }
 
     fn change(&mut self, props: Self::Properties) -> bool {
-        log::info!("updated entry to {:?}", props.entry);
-        self.entry = props.entry;
+        if props.entry_id == self.entry_id {
+            return false;
+        }
+        let entry_id = props.entry_id;
+        log::info!("updated entry to {:?}", entry_id);
+        self.entry_id = entry_id;
+        self.entry = props.entry.filter(|e| e.entry_id == entry_id);
+        self.completed = false;
+        self.link.send_message(match self.entry {
+            Some(_) => DetailMsg::GetCompleted,
+            None => DetailMsg::GetEntry,
+        });
         true
     }
 
     fn update(&mut self, msg: Self::Message) -> bool {
         match msg {
+            DetailMsg::GetEntry => {
+                let callback = self.link.callback(DetailMsg::ReceiveEntry);
+                match self.api.get_cache(self.entry_id, callback) {
+                    Ok(task) => self.entry_task = Some(task),
+                    Err(error) => {
+                        let retry = self.link.callback(|_| DetailMsg::GetEntry);
+                        notify_error(&mut self.notifier, "Could not load entry", &error, retry)
+                    }
+                }
+                false
+            }
+            DetailMsg::ReceiveEntry(response) => {
+                self.entry_task = None;
+                match response {
+                    Ok(Some(entry)) => {
+                        self.entry = Some(entry);
+                        self.link.send_message(DetailMsg::GetCompleted);
+                    }
+                    Ok(None) => {
+                        self.notifier
+                            .send(Notification::warning(format!("No entry with id {}", self.entry_id)));
+                    }
+                    Err(error) => {
+                        let retry = self.link.callback(|_| DetailMsg::GetEntry);
+                        notify_error(&mut self.notifier, "Could not load entry", &error, retry)
+                    }
+                }
+                true
+            }
             DetailMsg::CompletedChange(v) => {
                 log::info!("completed changed: {:?}", v);
                 match v {
                     yew::events::ChangeData::Value(s) =>{
-                        self.completed = !self.completed;
                         log::info!("completed checbox : change value: {:?}", s);
-                        log::info!("completed checbox : state: {:?}", self.completed);
-                            
-                            match &self.entry { 
-                                None => {
-                                    log::info!("completed checbox : entry_id: None");
-                                    false
-                                }
-                                Some(e) => {
-                                    log::info!("completed checbox : entry_id: {:?}", e.entry_id);
-                                    let server = host().unwrap();
-                                    let query = format!("http://{}/submit/completed", server);
-                                    let payload = CompletedPayload {
-                                        entry_id: e.entry_id,
-                                        state: self.completed,
-                                    };
-                                    // TODO can we use payload to deserialize here instead?
-                                    // currently payload value isn't used
-                                    let body = json!({"pcEntryID": payload.entry_id,
-                                                      "pcState": payload.state});
-                                    let request = Request::post(query)
-                                        .header("Content-Type", "application/json")
-                                        .body(Json(&body))
-                                        .expect("Could not build request.");
-                                    let callback = self.link.callback_once(
-                                        |response: Response<Json<Result<Vec<CompletedResponse>, anyhow::Error>>>| {
-                                            let Json(data) = response.into_body();
-                                            DetailMsg::CompletedResponse(data)
-                                        },
-                                    );
-                                    let task = FetchService::fetch(request, callback).expect("failed to start request");
-                                    self.submit_task = Some(task);
-                                    // e.entry_id ;
-                                    false
-                                }
-                            }
+                        self.link.send_message(DetailMsg::SubmitCompleted(!self.completed));
+                        false
                     }
                     _ =>{
                         log::info!("completed checkbox : not a value: {:?}", v);
@@ -155,54 +181,143 @@ impl Component for Detail {
                     }
                 }
             }
-            DetailMsg::CompletedResponse(d) => {
+            // shown right away, put back if the request fails
+            DetailMsg::SubmitCompleted(state) => {
+                match &self.entry { 
+                    None => {
+                        log::info!("completed checbox : entry_id: None");
+                        self.notifier.send(Notification::warning("No entry selected"));
+                        false
+                    }
+                    Some(e) => {
+                        log::info!("completed checbox : entry_id: {:?}", e.entry_id);
+                        let payload = CompletedPayload {
+                            entry_id: e.entry_id,
+                            state,
+                        };
+                        let callback = {
+                            let payload = payload.clone();
+                            self.link
+                                .callback(move |response| DetailMsg::CompletedResponse(payload.clone(), response))
+                        };
+                        match self.api.submit_completed(&payload, callback) {
+                            Ok(task) => {
+                                self.completed_task = Some(task);
+                                self.completed = state;
+                            }
+                            Err(error) => {
+                                let retry = self.link.callback_once(move |_| DetailMsg::SubmitCompleted(state));
+                                notify_error(&mut self.notifier, "Could not update completed", &error, retry)
+                            }
+                        }
+                        true
+                    }
+                }
+            }
+            DetailMsg::CompletedResponse(payload, d) => {
                 log::info!("completed response {:?}", d);
-                false
+                self.completed_task = None;
+                match d {
+                    Err(ApiError::Offline) => {
+                        self.offline.send(OfflineInput::Queue(OutboxItem::Completed(payload)));
+                        false
+                    }
+                    Err(error) => {
+                        let state = payload.state;
+                        // unless another entry is shown by now
+                        if payload.entry_id == self.entry_id {
+                            self.completed = !state;
+                        }
+                        let retry = self.link.callback_once(move |_| DetailMsg::SubmitCompleted(state));
+                        notify_error(&mut self.notifier, "Could not update completed", &error, retry);
+                        true
+                    }
+                    Ok(_) => false,
+                }
             }
             DetailMsg::GetCompleted => {
                 match &self.entry { 
                     None => { log::info!("no entry value"); }
                     Some(e) => {
-                        let server = host().unwrap();
-                        let query = format!("http://{}/get/completed/{:?}", server,e.entry_id).to_string();
-                        log::info!("submitting get completed : {:?}", query);
-                        let request = Request::get(&query)
-                            .body(Nothing)
-                            .expect("Could not build request.");
-                        let callback = self.link.callback_once(
-                            |response: Response<Json<Result<Vec<bool>, anyhow::Error>>>| {
-                                let Json(data) = response.into_body();
-                                DetailMsg::ReceiveCompleted(data)
-                            },
-                        );
-                        let task = FetchService::fetch(request, callback).expect("failed to start request");
-                        self.submit_task = Some(task);
+                        log::info!("submitting get completed : {:?}", e.entry_id);
+                        let callback = self.link.callback(DetailMsg::ReceiveCompleted);
+                        match self.api.get_completed(e.entry_id, callback) {
+                            Ok(task) => self.get_completed_task = Some(task),
+                            Err(error) => {
+                                let retry = self.link.callback(|_| DetailMsg::GetCompleted);
+                                notify_error(&mut self.notifier, "Could not load completed state", &error, retry)
+                            }
+                        }
                     }
                 }
                 false
             }
             DetailMsg::ReceiveCompleted(completed) => {
                 log::info!("received : {:?}", completed);
+                self.get_completed_task = None;
                 match completed {
                     Ok(result) => { 
-                        self.completed = result[0]; // TODO clean this up to be safe
+                        self.completed = result;
                         log::info!("completed is now: {:?}", self.completed);
                     }
                     Err(error) => {
                         log::info!("receive error, error is:");
                         log::info!("{}", &error.to_string());
-                        // Some(error.to_string());
+                        let retry = self.link.callback(|_| DetailMsg::GetCompleted);
+                        notify_error(&mut self.notifier, "Could not load completed state", &error, retry);
                     }
                 }
                 true
             }
+            // keys typed into the editor never get here, see `keymap::for_target`
+            DetailMsg::Key(Action::ToggleCompleted) => {
+                self.link.send_message(DetailMsg::SubmitCompleted(!self.completed));
+                false
+            }
+            DetailMsg::Key(Action::Tag) => {
+                let entry = match &self.entry {
+                    Some(entry) => entry,
+                    None => return false,
+                };
+                let title = entry.content.clone().unwrap_or_default();
+                if let Some(tags) = prompt_tags(&title) {
+                    let payload = TagsPayload {
+                        entry_id: entry.entry_id,
+                        tags,
+                    };
+                    let callback = {
+                        let payload = payload.clone();
+                        self.link
+                            .callback(move |response| DetailMsg::TagsResponse(payload.clone(), response))
+                    };
+                    match self.api.submit_tags(&payload, callback) {
+                        Ok(task) => self.tags_task = Some(task),
+                        Err(error) => self.notifier.send(Notification::error(format!("Could not add tags: {}", error))),
+                    }
+                }
+                false
+            }
+            DetailMsg::Key(_) => false,
+            DetailMsg::TagsResponse(payload, response) => {
+                self.tags_task = None;
+                match response {
+                    Ok(_) => self
+                        .notifier
+                        .send(Notification::success(format!("Tagged {}", payload.tags.join(", ")))),
+                    Err(ApiError::Offline) => {
+                        self.offline.send(OfflineInput::Queue(OutboxItem::Tags(payload)));
+                        self.notifier.send(Notification::info("Server unreachable, tags will be added once it is back"));
+                    }
+                    Err(error) => self.notifier.send(Notification::error(format!("Could not add tags: {}", error))),
+                }
+                false
+            }
         }
     }
 
     
-    fn rendered(&mut self, first_render: bool) {
+    fn rendered(&mut self, _first_render: bool) {
         log::info!("calling init_ace");
-        // self.ace_editor = Some(init_ace());
         init_ace();
         log::info!("called init_ace");
     }
@@ -223,6 +338,7 @@ impl Component for Detail {
         let note_content = match &self.entry {
             Some(entry) => if entry.url.is_none() { title } 
                            else { ["# Notes on", &title].join(" ") },
+            None if self.entry_task.is_some() => "Loading...".to_string(),
             None => "No Entry Selected".to_string(),
         };
         log::info!("Screen {:?}", src);
@@ -1,5 +1,4 @@
 use wasm_bindgen::prelude::*;
-use yew::web_sys::console;
 
 /*
 #[wasm_bindgen]
@@ -17,3 +16,17 @@ extern "C" {
     #[wasm_bindgen]
     pub fn ace_add_callback(editor: JsValue, callback: &dyn Fn(JsValue) -> ()) -> JsValue;
 }
+
+// IndexedDB helpers from index.html. `callback` is called once, with null
+// when there is no value.
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen]
+    pub fn idb_get(key: &str, callback: JsValue);
+
+    #[wasm_bindgen]
+    pub fn idb_put(key: &str, value: &str);
+
+    #[wasm_bindgen]
+    pub fn idb_trim(prefix: &str, keep: u32);
+}
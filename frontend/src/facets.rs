@@ -0,0 +1,105 @@
+use crate::tags::*;
+use chrono::NaiveDate;
+use yew::prelude::*;
+use yew::Properties;
+
+/// A facet removed from the chip bar.
+#[derive(Debug, Clone)]
+pub enum FacetChange {
+    Search,
+    Dates,
+    Completed,
+    Tags(TagSelection),
+    All,
+}
+
+/// Every filter narrowing the gallery, shown as removable chips: the search
+/// text, the timeline range, hidden completed entries and the selected tags.
+pub struct Facets {
+    link: ComponentLink<Self>,
+    props: FacetProps,
+}
+
+#[derive(Properties, PartialEq, Clone)]
+pub struct FacetProps {
+    pub search: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub hide_completed: bool,
+    pub selected_tags: TagSelection,
+    pub on_change: Callback<FacetChange>,
+}
+
+impl FacetProps {
+    fn is_empty(&self) -> bool {
+        self.search.is_none()
+            && self.start_date.is_none()
+            && self.end_date.is_none()
+            && !self.hide_completed
+            && self.selected_tags.is_empty()
+    }
+}
+
+fn date_range(start: Option<NaiveDate>, end: Option<NaiveDate>) -> Option<String> {
+    match (start, end) {
+        (Some(start), Some(end)) => Some(format!("{} – {}", start, end)),
+        (Some(start), None) => Some(format!("after {}", start)),
+        (None, Some(end)) => Some(format!("before {}", end)),
+        (None, None) => None,
+    }
+}
+
+impl Component for Facets {
+    type Message = FacetChange;
+    type Properties = FacetProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self { link, props }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn update(&mut self, change: Self::Message) -> ShouldRender {
+        self.props.on_change.emit(change);
+        false
+    }
+
+    fn view(&self) -> Html {
+        let props = &self.props;
+        if props.is_empty() {
+            return html! {};
+        }
+        let chip = |label: String, change: FacetChange| {
+            html! {
+                <span class="tag-chip facet-chip">
+                    { label }
+                    <button class="tag-chip-remove"
+                        onclick=self.link.callback(move |_| change.clone())>{ "✕" }</button>
+                </span>
+            }
+        };
+        html! {
+            <div class="facet-chips">
+                { for props.search.iter().map(|search| chip(format!("“{}”", search), FacetChange::Search)) }
+                { for date_range(props.start_date, props.end_date).map(|range| chip(range, FacetChange::Dates)) }
+                {
+                    if props.hide_completed {
+                        chip("not completed".to_string(), FacetChange::Completed)
+                    } else {
+                        html! {}
+                    }
+                }
+                <TagChips selected=props.selected_tags.clone()
+                    on_change=self.link.callback(FacetChange::Tags)/>
+                <button class="tag-chip-clear" onclick=self.link.callback(|_| FacetChange::All)>{ "Clear all" }</button>
+            </div>
+        }
+    }
+}
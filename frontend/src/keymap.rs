@@ -0,0 +1,352 @@
+use crate::prefs;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, KeyboardEvent};
+use yew::agent::{Agent, AgentLink, Context, HandlerId};
+use yew::prelude::*;
+use yew::utils::document;
+
+// keys of a sequence like `g g` have to follow each other within this (ms)
+const SEQUENCE_TIMEOUT: i64 = 1000;
+
+/// Something a key binding does. The gallery and detail views each handle
+/// the ones that make sense there.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Next,
+    Prev,
+    Open,
+    ToggleCompleted,
+    Tag,
+    Search,
+    First,
+    Last,
+    Help,
+    Close,
+    Palette,
+}
+
+impl Action {
+    pub const ALL: [Action; 11] = [
+        Action::Next,
+        Action::Prev,
+        Action::Open,
+        Action::ToggleCompleted,
+        Action::Tag,
+        Action::Search,
+        Action::First,
+        Action::Last,
+        Action::Help,
+        Action::Close,
+        Action::Palette,
+    ];
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Action::Next => "Next card",
+            Action::Prev => "Previous card",
+            Action::Open => "Open the card",
+            Action::ToggleCompleted => "Toggle completed",
+            Action::Tag => "Add tags",
+            Action::Search => "Search",
+            Action::First => "First card",
+            Action::Last => "Last card",
+            Action::Help => "Show shortcuts",
+            Action::Close => "Close overlay",
+            Action::Palette => "Command palette",
+        }
+    }
+
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Next => &["j", "ArrowDown"],
+            Action::Prev => &["k", "ArrowUp"],
+            Action::Open => &["Enter"],
+            Action::ToggleCompleted => &["x"],
+            Action::Tag => &["t"],
+            Action::Search => &["/"],
+            Action::First => &["g g"],
+            Action::Last => &["G"],
+            Action::Help => &["?"],
+            Action::Close => &["Escape"],
+            Action::Palette => &["Ctrl+k"],
+        }
+    }
+}
+
+/// Key bindings per action. A binding is a space separated sequence of
+/// `KeyboardEvent.key` values, e.g. `g g` or `ArrowDown`. `Ctrl+` in front
+/// of a key also matches Cmd on macOS.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Keymap {
+    bindings: HashMap<Action, Vec<String>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .iter()
+            .map(|action| {
+                let keys = action.default_keys().iter().map(|k| k.to_string()).collect();
+                (*action, keys)
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// The saved keymap, defaults for actions it doesn't mention.
+    pub fn load() -> Self {
+        let mut keymap = Self::default();
+        let saved: Option<HashMap<Action, Vec<String>>> = prefs::get(prefs::KEYMAP)
+            .and_then(|json| serde_json::from_str(&json).ok());
+        keymap.bindings.extend(saved.unwrap_or_default());
+        keymap
+    }
+
+    pub fn save(&self) {
+        match serde_json::to_string(&self.bindings) {
+            Ok(json) => prefs::set(prefs::KEYMAP, &json),
+            Err(e) => log::info!("could not save keymap: {}", e),
+        }
+    }
+
+    pub fn reset() {
+        prefs::remove(prefs::KEYMAP);
+    }
+
+    pub fn keys(&self, action: Action) -> &[String] {
+        self.bindings.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn set_keys(&mut self, action: Action, keys: Vec<String>) {
+        self.bindings.insert(action, keys);
+    }
+
+    fn lookup(&self, pending: &[String]) -> Lookup {
+        let mut prefix = false;
+        for (action, bindings) in &self.bindings {
+            for binding in bindings {
+                let keys: Vec<&str> = binding.split_whitespace().collect();
+                if keys == pending {
+                    return Lookup::Action(*action);
+                }
+                prefix |= keys.len() > pending.len() && keys[..pending.len()] == *pending;
+            }
+        }
+        if prefix {
+            Lookup::Prefix
+        } else {
+            Lookup::None
+        }
+    }
+}
+
+enum Lookup {
+    Action(Action),
+    Prefix,
+    None,
+}
+
+fn with_ctrl(e: &KeyboardEvent) -> bool {
+    e.ctrl_key() || e.meta_key()
+}
+
+fn key_name(e: &KeyboardEvent) -> String {
+    let key = match e.key().as_str() {
+        " " => "Space".to_string(),
+        key => key.to_string(),
+    };
+    if with_ctrl(e) {
+        format!("Ctrl+{}", key)
+    } else {
+        key
+    }
+}
+
+/// Keys typed into a form field or the Ace editor (which has its own vim
+/// bindings) never trigger shortcuts, neither does Enter on a link or button.
+/// Ctrl combinations aren't typing, they work in form fields too.
+fn for_target(e: &KeyboardEvent) -> bool {
+    let target = match e.target().and_then(|t| t.dyn_into::<Element>().ok()) {
+        Some(target) => target,
+        None => return false,
+    };
+    if target.closest(".ace_editor").ok().flatten().is_some() {
+        return true;
+    }
+    if with_ctrl(e) {
+        return false;
+    }
+    let tag = target.tag_name();
+    matches!(tag.as_str(), "INPUT" | "TEXTAREA" | "SELECT")
+        || (matches!(tag.as_str(), "A" | "BUTTON") && matches!(e.key().as_str(), "Enter" | " "))
+        || target.get_attribute("contenteditable").is_some()
+}
+
+struct Sequence {
+    keymap: Keymap,
+    pending: Vec<String>,
+    last_ms: i64,
+}
+
+impl Sequence {
+    fn key(&mut self, key: String) -> Lookup {
+        let now = chrono::Utc::now().timestamp_millis();
+        if now - self.last_ms > SEQUENCE_TIMEOUT {
+            self.pending.clear();
+        }
+        self.last_ms = now;
+        self.pending.push(key.clone());
+        let mut lookup = self.keymap.lookup(&self.pending);
+        if matches!(lookup, Lookup::None) && self.pending.len() > 1 {
+            // a broken sequence, the last key may start something new
+            self.pending = vec![key];
+            lookup = self.keymap.lookup(&self.pending);
+        }
+        if !matches!(lookup, Lookup::Prefix) {
+            self.pending.clear();
+        }
+        lookup
+    }
+}
+
+pub enum KeyBindingsInput {
+    /// the keymap was changed in the settings
+    Reload,
+}
+
+/// Listens to keydown on the document, resolves key sequences with the
+/// `Keymap` and sends the resulting `Action`s to every subscriber.
+pub struct KeyBindings {
+    link: AgentLink<Self>,
+    subscribers: HashSet<HandlerId>,
+    sequence: Rc<RefCell<Sequence>>,
+    on_keydown: Closure<dyn Fn(KeyboardEvent)>,
+}
+
+impl Agent for KeyBindings {
+    type Reach = Context<Self>;
+    type Message = Action;
+    type Input = KeyBindingsInput;
+    type Output = Action;
+
+    fn create(link: AgentLink<Self>) -> Self {
+        let sequence = Rc::new(RefCell::new(Sequence {
+            keymap: Keymap::load(),
+            pending: Vec::new(),
+            last_ms: 0,
+        }));
+        let action = link.callback(|action| action);
+        let keys = sequence.clone();
+        let on_keydown = Closure::wrap(Box::new(move |e: KeyboardEvent| {
+            if e.default_prevented() || e.alt_key() || matches!(e.key().as_str(), "Control" | "Meta" | "Shift" | "Alt") || for_target(&e) {
+                return;
+            }
+            // decided here, the event can't be cancelled once this returns
+            let lookup = keys.borrow_mut().key(key_name(&e));
+            match lookup {
+                Lookup::Action(a) => {
+                    e.prevent_default();
+                    action.emit(a);
+                }
+                Lookup::Prefix => e.prevent_default(),
+                Lookup::None => {}
+            }
+        }) as Box<dyn Fn(KeyboardEvent)>);
+        if let Err(e) = document().add_event_listener_with_callback("keydown", on_keydown.as_ref().unchecked_ref()) {
+            log::info!("could not listen to key events: {:?}", e);
+        }
+        Self {
+            link,
+            subscribers: HashSet::new(),
+            sequence,
+            on_keydown,
+        }
+    }
+
+    fn update(&mut self, action: Self::Message) {
+        log::info!("key action {:?}", action);
+        for subscriber in &self.subscribers {
+            self.link.respond(*subscriber, action);
+        }
+    }
+
+    fn connected(&mut self, id: HandlerId) {
+        if id.is_respondable() {
+            self.subscribers.insert(id);
+        }
+    }
+
+    fn handle_input(&mut self, msg: Self::Input, _id: HandlerId) {
+        match msg {
+            KeyBindingsInput::Reload => self.sequence.borrow_mut().keymap = Keymap::load(),
+        }
+    }
+
+    fn disconnected(&mut self, id: HandlerId) {
+        self.subscribers.remove(&id);
+    }
+
+    fn destroy(&mut self) {
+        let _ = document().remove_event_listener_with_callback("keydown", self.on_keydown.as_ref().unchecked_ref());
+    }
+}
+
+#[derive(Properties, PartialEq, Clone)]
+pub struct HelpProps {
+    pub on_close: Callback<()>,
+}
+
+/// `?` overlay listing the current bindings.
+pub struct KeyHelp {
+    props: HelpProps,
+    keymap: Keymap,
+}
+
+impl Component for KeyHelp {
+    type Message = ();
+    type Properties = HelpProps;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Self {
+            props,
+            keymap: Keymap::load(),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        false
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        let on_close = self.props.on_close.clone();
+        html! {
+            <div class="overlay" onclick=Callback::from(move |_| on_close.emit(()))>
+                <div class="overlay-panel shadow rounded" onclick=Callback::from(|e: MouseEvent| e.stop_propagation())>
+                    <h5>{ "Keyboard shortcuts" }</h5>
+                    <table class="key-help">
+                        { for Action::ALL.iter().map(|action| html! {
+                            <tr>
+                                <td>{ for self.keymap.keys(*action).iter().map(|keys| html! { <kbd>{ keys }</kbd> }) }</td>
+                                <td>{ action.describe() }</td>
+                            </tr>
+                        }) }
+                    </table>
+                    <p class="settings-hint">{ "Shortcuts can be changed in the settings." }</p>
+                </div>
+            </div>
+        }
+    }
+}
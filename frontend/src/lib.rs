@@ -1,17 +1,35 @@
 #![recursion_limit = "1024"]
 // https://github.com/yewstack/yew/issues/513
+// html! expands component props into bare statements that clippy flags
+#![allow(clippy::unnecessary_operation)]
 
 mod add_note;
-mod api;
+pub mod api;
+mod api_cache;
 mod app;
 mod app_router;
+mod batch;
+mod card;
 mod cards;
 mod detail;
 mod external;
+mod facets;
+mod keymap;
+mod notifications;
+mod offline;
+mod palette;
+mod prefs;
+#[allow(dead_code)] // placeholder view
 mod queue;
+mod saved_searches;
+mod search;
+mod search_box;
 mod settings;
+pub mod sort;
+#[allow(dead_code)] // placeholder view
 mod space;
 mod tags;
+mod thumbnail;
 mod timeline;
 
 use wasm_bindgen::prelude::*;
@@ -0,0 +1,211 @@
+use crate::api::ApiError;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use yew::agent::{Agent, AgentLink, Bridge, Bridged, Context, Dispatcher, HandlerId};
+use yew::prelude::*;
+use yew::services::timeout::{TimeoutService, TimeoutTask};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn class(&self) -> &'static str {
+        match self {
+            Severity::Info => "toast-info",
+            Severity::Success => "toast-success",
+            Severity::Warning => "toast-warning",
+            Severity::Error => "toast-error",
+        }
+    }
+
+    // how long a toast without a retry action stays up
+    fn lifetime(&self) -> Duration {
+        match self {
+            Severity::Info | Severity::Success => Duration::from_secs(4),
+            Severity::Warning => Duration::from_secs(8),
+            Severity::Error => Duration::from_secs(12),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub severity: Severity,
+    pub message: String,
+    /// re-issues the failed request, shown as a "Retry" button
+    pub retry: Option<Callback<()>>,
+}
+
+impl Notification {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            retry: None,
+        }
+    }
+
+    pub fn info(message: impl Into<String>) -> Self {
+        Self::new(Severity::Info, message)
+    }
+
+    pub fn success(message: impl Into<String>) -> Self {
+        Self::new(Severity::Success, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn with_retry(mut self, retry: Callback<()>) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+}
+
+/// Error toast for a failed request, `retry` is emitted by its Retry button.
+pub fn notify_error(notifier: &mut Dispatcher<NotificationBus>, what: &str, error: &ApiError, retry: Callback<()>) {
+    notifier.send(Notification::error(format!("{}: {}", what, error)).with_retry(retry));
+}
+
+/// Broadcasts notifications from any component to the `Toasts` view.
+/// Components push with a `Dispatcher<NotificationBus>`, `Toasts` listens
+/// through a bridge.
+pub struct NotificationBus {
+    link: AgentLink<Self>,
+    subscribers: HashSet<HandlerId>,
+}
+
+impl Agent for NotificationBus {
+    type Reach = Context<Self>;
+    type Message = ();
+    type Input = Notification;
+    type Output = Notification;
+
+    fn create(link: AgentLink<Self>) -> Self {
+        Self {
+            link,
+            subscribers: HashSet::new(),
+        }
+    }
+
+    fn update(&mut self, _msg: Self::Message) {}
+
+    fn connected(&mut self, id: HandlerId) {
+        if id.is_respondable() {
+            self.subscribers.insert(id);
+        }
+    }
+
+    fn handle_input(&mut self, msg: Self::Input, _id: HandlerId) {
+        log::info!("notification: {:?} {}", msg.severity, msg.message);
+        for subscriber in &self.subscribers {
+            self.link.respond(*subscriber, msg.clone());
+        }
+    }
+
+    fn disconnected(&mut self, id: HandlerId) {
+        self.subscribers.remove(&id);
+    }
+}
+
+pub enum ToastsMsg {
+    Push(Notification),
+    Dismiss(usize),
+    Retry(usize),
+}
+
+pub struct Toasts {
+    link: ComponentLink<Self>,
+    _bus: Box<dyn Bridge<NotificationBus>>,
+    toasts: Vec<(usize, Notification)>,
+    timeouts: HashMap<usize, TimeoutTask>,
+    next_id: usize,
+}
+
+impl Component for Toasts {
+    type Message = ToastsMsg;
+    type Properties = ();
+
+    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let bus = NotificationBus::bridge(link.callback(ToastsMsg::Push));
+        Self {
+            link,
+            _bus: bus,
+            toasts: Vec::new(),
+            timeouts: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            ToastsMsg::Push(notification) => {
+                let id = self.next_id;
+                self.next_id += 1;
+                // toasts with a retry action wait for the user
+                if notification.retry.is_none() {
+                    let dismiss = self.link.callback(move |_| ToastsMsg::Dismiss(id));
+                    let lifetime = notification.severity.lifetime();
+                    self.timeouts.insert(id, TimeoutService::spawn(lifetime, dismiss));
+                }
+                self.toasts.push((id, notification));
+                true
+            }
+            ToastsMsg::Dismiss(id) => {
+                self.timeouts.remove(&id);
+                self.toasts.retain(|(toast_id, _)| *toast_id != id);
+                true
+            }
+            ToastsMsg::Retry(id) => {
+                if let Some((_, notification)) = self.toasts.iter().find(|(toast_id, _)| *toast_id == id) {
+                    if let Some(retry) = &notification.retry {
+                        retry.emit(());
+                    }
+                }
+                self.link.send_message(ToastsMsg::Dismiss(id));
+                false
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div class="toasts">
+                { for self.toasts.iter().map(|(id, notification)| {
+                    let id = *id;
+                    html! {
+                        <div class=format!("toast-item shadow rounded {}", notification.severity.class())>
+                            <span class="toast-message">{ &notification.message }</span>
+                            {
+                                if notification.retry.is_some() {
+                                    html! {
+                                        <button class="toast-button"
+                                            onclick=self.link.callback(move |_| ToastsMsg::Retry(id))>{ "Retry" }</button>
+                                    }
+                                } else {
+                                    html! {}
+                                }
+                            }
+                            <button class="toast-button"
+                                onclick=self.link.callback(move |_| ToastsMsg::Dismiss(id))>{ "✕" }</button>
+                        </div>
+                    }
+                }) }
+            </div>
+        }
+    }
+}
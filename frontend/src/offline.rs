@@ -0,0 +1,322 @@
+use crate::api::*;
+use crate::external::{idb_get, idb_put, idb_trim};
+use crate::notifications::*;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::time::Duration;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use yew::agent::{Agent, AgentLink, Bridge, Bridged, Context, Dispatched, Dispatcher, HandlerId};
+use yew::prelude::*;
+use yew::services::interval::{IntervalService, IntervalTask};
+use yew::utils::window;
+
+// IndexedDB keys
+const OUTBOX_KEY: &str = "outbox";
+const RESPONSE_PREFIX: &str = "response:";
+// saved GET responses, older ones are trimmed
+const RESPONSE_LIMIT: u32 = 50;
+// saves between two trims, the store may run over the limit by this many
+const TRIM_EVERY: u32 = 10;
+const REPLAY_INTERVAL: Duration = Duration::from_secs(15);
+
+// saved responses
+
+thread_local! {
+    static SAVES: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Keep a GET response body so it can be served while the server is down.
+pub fn save_response(key: &str, body: &str) {
+    idb_put(&format!("{}{}", RESPONSE_PREFIX, key), body);
+    // on the first save of the session and every `TRIM_EVERY` after that
+    if SAVES.with(|saves| saves.replace(saves.get() + 1)).is_multiple_of(TRIM_EVERY) {
+        idb_trim(RESPONSE_PREFIX, RESPONSE_LIMIT);
+    }
+}
+
+/// Body saved by `save_response` for `key`, if any.
+pub fn load_response(key: &str, callback: impl FnOnce(Option<String>) + 'static) {
+    let callback = Closure::once_into_js(move |value: JsValue| callback(value.as_string()));
+    idb_get(&format!("{}{}", RESPONSE_PREFIX, key), callback);
+}
+
+// connectivity
+
+thread_local! {
+    static ONLINE: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Called by `ApiClient` for every response. Changes are forwarded to the
+/// `Offline` agent so the indicator and outbox replay can react.
+pub fn report_online(online: bool) {
+    if ONLINE.with(|state| state.replace(online)) != online {
+        Offline::dispatcher().send(OfflineInput::Online(online));
+    }
+}
+
+// outbox
+
+/// A write made while the server was unreachable.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum OutboxItem {
+    Note(AddNotePayload),
+    Completed(CompletedPayload),
+    Tags(TagsPayload),
+    RemoveTags(TagsPayload),
+}
+
+impl OutboxItem {
+    fn describe(&self) -> &'static str {
+        match self {
+            OutboxItem::Note(_) => "note",
+            OutboxItem::Completed(_) => "completed state",
+            OutboxItem::Tags(_) => "tags",
+            OutboxItem::RemoveTags(_) => "tag removal",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyncState {
+    pub online: bool,
+    /// outbox items not yet accepted by the server
+    pub pending: usize,
+}
+
+#[derive(Debug)]
+pub enum OfflineInput {
+    Queue(OutboxItem),
+    Online(bool),
+}
+
+pub enum OfflineMsg {
+    Loaded(Vec<OutboxItem>),
+    Replay,
+    Replayed(ApiResult<i64>),
+}
+
+/// Holds the outbox. Items are persisted to IndexedDB and replayed in order
+/// against `/submit/*` once the server answers again. Subscribers get the
+/// current `SyncState` on connect and on every change.
+pub struct Offline {
+    link: AgentLink<Self>,
+    subscribers: HashSet<HandlerId>,
+    outbox: Vec<OutboxItem>,
+    // items queued before the saved outbox was read back
+    loaded: bool,
+    online: bool,
+    api: ApiClient,
+    replay_task: Option<ApiTask>,
+    notifier: Dispatcher<NotificationBus>,
+    _interval: IntervalTask,
+    // window "online" listener
+    on_online: Closure<dyn Fn()>,
+}
+
+impl Offline {
+    fn state(&self) -> SyncState {
+        SyncState {
+            online: self.online,
+            pending: self.outbox.len(),
+        }
+    }
+
+    fn broadcast(&self) {
+        for subscriber in &self.subscribers {
+            self.link.respond(*subscriber, self.state());
+        }
+    }
+
+    fn persist(&self) {
+        match serde_json::to_string(&self.outbox) {
+            Ok(json) => idb_put(OUTBOX_KEY, &json),
+            Err(e) => log::info!("could not save outbox: {}", e),
+        }
+    }
+
+    fn replay(&mut self) {
+        if !self.loaded || self.replay_task.is_some() {
+            return;
+        }
+        let item = match self.outbox.first() {
+            Some(item) => item.clone(),
+            None => return,
+        };
+        log::info!("replaying {:?}", item);
+        let callback = self.link.callback(OfflineMsg::Replayed);
+        let task = match &item {
+            OutboxItem::Note(payload) => self.api.submit_note(payload, callback),
+            OutboxItem::Completed(payload) => self.api.submit_completed(payload, callback),
+            OutboxItem::Tags(payload) => self.api.submit_tags(payload, callback),
+            OutboxItem::RemoveTags(payload) => self.api.remove_tags(payload, callback),
+        };
+        match task {
+            Ok(task) => self.replay_task = Some(task),
+            Err(error) => log::info!("could not replay {}: {}", item.describe(), error),
+        }
+    }
+}
+
+impl Agent for Offline {
+    type Reach = Context<Self>;
+    type Message = OfflineMsg;
+    type Input = OfflineInput;
+    type Output = SyncState;
+
+    fn create(link: AgentLink<Self>) -> Self {
+        let loaded = link.callback(OfflineMsg::Loaded);
+        let restore = Closure::once_into_js(move |value: JsValue| {
+            let outbox = value
+                .as_string()
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default();
+            loaded.emit(outbox);
+        });
+        idb_get(OUTBOX_KEY, restore);
+
+        let replay = link.callback(|_| OfflineMsg::Replay);
+        let on_online = Closure::wrap(Box::new(move || replay.emit(())) as Box<dyn Fn()>);
+        if let Err(e) = window().add_event_listener_with_callback("online", on_online.as_ref().unchecked_ref()) {
+            log::info!("could not listen to online events: {:?}", e);
+        }
+        Self {
+            _interval: IntervalService::spawn(REPLAY_INTERVAL, link.callback(|_| OfflineMsg::Replay)),
+            link,
+            subscribers: HashSet::new(),
+            outbox: Vec::new(),
+            loaded: false,
+            online: true,
+            api: ApiClient::new(),
+            replay_task: None,
+            notifier: NotificationBus::dispatcher(),
+            on_online,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) {
+        match msg {
+            OfflineMsg::Loaded(saved) => {
+                let queued = std::mem::replace(&mut self.outbox, saved);
+                self.loaded = true;
+                if !queued.is_empty() {
+                    self.outbox.extend(queued);
+                    self.persist();
+                }
+                self.broadcast();
+                self.replay();
+            }
+            OfflineMsg::Replay => self.replay(),
+            OfflineMsg::Replayed(response) => {
+                self.replay_task = None;
+                match response {
+                    Ok(_) => {
+                        self.outbox.remove(0);
+                        if self.outbox.is_empty() {
+                            self.notifier.send(Notification::success("Offline changes synced"));
+                        }
+                        self.persist();
+                        self.broadcast();
+                        self.replay();
+                    }
+                    // still unreachable, try again on the next tick
+                    Err(ApiError::Offline) => {}
+                    Err(ApiError::Status(code)) if code >= 500 => {}
+                    Err(error) => {
+                        // the server rejected it, replaying won't help
+                        let item = self.outbox.remove(0);
+                        self.notifier.send(Notification::error(format!(
+                            "Could not sync offline {}: {}",
+                            item.describe(),
+                            error
+                        )));
+                        self.persist();
+                        self.broadcast();
+                        self.replay();
+                    }
+                }
+            }
+        }
+    }
+
+    fn connected(&mut self, id: HandlerId) {
+        if id.is_respondable() {
+            self.subscribers.insert(id);
+            self.link.respond(id, self.state());
+        }
+    }
+
+    fn handle_input(&mut self, msg: Self::Input, _id: HandlerId) {
+        match msg {
+            OfflineInput::Queue(item) => {
+                log::info!("queued {:?}", item);
+                self.outbox.push(item);
+                if self.loaded {
+                    self.persist();
+                }
+            }
+            OfflineInput::Online(online) => {
+                self.online = online;
+                if online {
+                    self.replay();
+                }
+            }
+        }
+        self.broadcast();
+    }
+
+    fn disconnected(&mut self, id: HandlerId) {
+        self.subscribers.remove(&id);
+    }
+
+    fn destroy(&mut self) {
+        let _ = window().remove_event_listener_with_callback("online", self.on_online.as_ref().unchecked_ref());
+    }
+}
+
+// indicator
+
+/// Navbar badge showing whether the server is reachable and how many
+/// offline changes are waiting to be synced.
+pub struct SyncIndicator {
+    _offline: Box<dyn Bridge<Offline>>,
+    state: SyncState,
+}
+
+impl Component for SyncIndicator {
+    type Message = SyncState;
+    type Properties = ();
+
+    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            _offline: Offline::bridge(link.callback(|state| state)),
+            state: SyncState {
+                online: true,
+                pending: 0,
+            },
+        }
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn update(&mut self, state: Self::Message) -> ShouldRender {
+        let changed = self.state != state;
+        self.state = state;
+        changed
+    }
+
+    fn view(&self) -> Html {
+        let (class, label) = match (self.state.online, self.state.pending) {
+            (true, 0) => ("sync-synced", "Synced".to_string()),
+            (true, pending) => ("sync-pending", format!("Syncing {}", pending)),
+            (false, 0) => ("sync-offline", "Offline".to_string()),
+            (false, pending) => ("sync-offline", format!("Offline - {} pending", pending)),
+        };
+        html! {
+            <span class=format!("sync-indicator {}", class)>{ label }</span>
+        }
+    }
+}
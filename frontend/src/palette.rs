@@ -0,0 +1,318 @@
+use crate::api::{Cache, SortDir};
+use crate::app_router::AppRoute;
+use crate::prefs;
+use crate::sort::{GallerySort, SortKey};
+use yew::prelude::*;
+use yew::Properties;
+use yew_router::prelude::*;
+
+// palette items shown at once
+const RESULT_LIMIT: usize = 12;
+// picked items remembered for ranking
+const RECENT_LIMIT: usize = 10;
+
+/// What picking a palette item does, run by `App`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// the gallery with its current filters
+    Gallery,
+    /// any other page, by url
+    Route(String),
+    /// add or remove a tag filter
+    Tag(String),
+    /// the detail page of a loaded entry
+    Open(i32),
+    HideCompleted(bool),
+    Sort(GallerySort),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteItem {
+    // stable across sessions, remembered for the recent items
+    id: String,
+    label: String,
+    // also matched, e.g. the url of an entry
+    hint: Option<String>,
+    kind: &'static str,
+    command: Command,
+}
+
+impl PaletteItem {
+    fn new(id: String, label: String, kind: &'static str, command: Command) -> Self {
+        Self {
+            id,
+            label,
+            hint: None,
+            kind,
+            command,
+        }
+    }
+}
+
+fn route_url(route: AppRoute) -> String {
+    Route::<()>::from(route).route
+}
+
+/// Everything the palette can do: pages, actions, tag filters from
+/// `/all/tags` and the loaded entries.
+pub fn items(
+    tags: &[String],
+    selected_tags: &[String],
+    entries: &[Cache],
+    hide_completed: bool,
+    sort: GallerySort,
+) -> Vec<PaletteItem> {
+    let mut items = vec![PaletteItem::new("gallery".to_string(), "Gallery".to_string(), "page", Command::Gallery)];
+    for (name, route) in [("Settings", AppRoute::Settings), ("Queue", AppRoute::Queue), ("Space", AppRoute::Space)] {
+        let url = route_url(route);
+        items.push(PaletteItem::new(format!("route:{}", url), name.to_string(), "page", Command::Route(url)));
+    }
+    items.push(PaletteItem::new(
+        "new_note".to_string(),
+        "New note".to_string(),
+        "action",
+        Command::Route(route_url(AppRoute::AddNote)),
+    ));
+    let completed = if hide_completed {
+        "Show completed entries"
+    } else {
+        "Hide completed entries"
+    };
+    items.push(PaletteItem::new(
+        "hide_completed".to_string(),
+        completed.to_string(),
+        "action",
+        Command::HideCompleted(!hide_completed),
+    ));
+    for key in SortKey::ALL.iter().copied() {
+        for dir in [SortDir::Fwd, SortDir::Rev] {
+            let next = GallerySort { key, dir };
+            if next == sort {
+                continue;
+            }
+            let (key_param, dir_param) = next.params();
+            let order = match dir {
+                SortDir::Fwd => "ascending",
+                SortDir::Rev => "descending",
+            };
+            items.push(PaletteItem::new(
+                format!("sort:{} {}", key_param, dir_param),
+                format!("Sort by {}, {}", key.label().to_lowercase(), order),
+                "action",
+                Command::Sort(next),
+            ));
+        }
+    }
+    for tag in tags {
+        let label = if selected_tags.contains(tag) {
+            format!("Remove tag filter {}", tag)
+        } else {
+            format!("Filter by tag {}", tag)
+        };
+        items.push(PaletteItem::new(format!("tag:{}", tag), label, "tag", Command::Tag(tag.clone())));
+    }
+    for entry in entries {
+        let title = entry.content.clone().unwrap_or_default();
+        let mut item = PaletteItem::new(
+            format!("entry:{}", entry.entry_id),
+            if title.is_empty() { format!("Entry {}", entry.entry_id) } else { title },
+            "entry",
+            Command::Open(entry.entry_id),
+        );
+        item.hint = entry.url.clone();
+        items.push(item);
+    }
+    items
+}
+
+/// Score of `query` as a case-insensitive subsequence of `text`, `None`
+/// when it isn't one. Consecutive characters and word starts count more.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last: Option<usize> = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = pos + text[pos..].iter().position(|t| *t == c)?;
+        score += 1;
+        if last == Some(found.wrapping_sub(1)) {
+            score += 3;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 2;
+        }
+        last = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
+
+fn recent() -> Vec<String> {
+    prefs::get(prefs::PALETTE_RECENT)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub enum PaletteMsg {
+    Input(String),
+    KeyDown(KeyboardEvent),
+    Run(usize),
+    Close,
+}
+
+/// Ctrl-K overlay: fuzzy search over `items`, recently picked ones first.
+pub struct Palette {
+    link: ComponentLink<Self>,
+    props: PaletteProps,
+    query: String,
+    selected: usize,
+    recent: Vec<String>,
+    input: NodeRef,
+}
+
+#[derive(Properties, PartialEq, Clone)]
+pub struct PaletteProps {
+    pub items: Vec<PaletteItem>,
+    pub on_command: Callback<Command>,
+    pub on_close: Callback<()>,
+}
+
+impl Palette {
+    fn results(&self) -> Vec<&PaletteItem> {
+        let rank = |item: &PaletteItem| self.recent.iter().position(|id| *id == item.id).unwrap_or(usize::MAX);
+        let mut found: Vec<(usize, i32, &PaletteItem)> = self
+            .props
+            .items
+            .iter()
+            .filter_map(|item| {
+                let score = fuzzy_score(&self.query, &item.label)
+                    .max(item.hint.as_deref().and_then(|hint| fuzzy_score(&self.query, hint)))?;
+                Some((rank(item), score, item))
+            })
+            .collect();
+        // stable, items without a recent rank keep their order for an empty query
+        found.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        found.into_iter().take(RESULT_LIMIT).map(|(_, _, item)| item).collect()
+    }
+
+    fn run(&mut self, index: usize) {
+        let item = match self.results().get(index) {
+            Some(item) => (*item).clone(),
+            None => return,
+        };
+        self.recent.retain(|id| *id != item.id);
+        self.recent.insert(0, item.id);
+        self.recent.truncate(RECENT_LIMIT);
+        match serde_json::to_string(&self.recent) {
+            Ok(json) => prefs::set(prefs::PALETTE_RECENT, &json),
+            Err(e) => log::info!("could not save palette history: {}", e),
+        }
+        self.props.on_close.emit(());
+        self.props.on_command.emit(item.command);
+    }
+}
+
+impl Component for Palette {
+    type Message = PaletteMsg;
+    type Properties = PaletteProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            link,
+            props,
+            query: String::new(),
+            selected: 0,
+            recent: recent(),
+            input: NodeRef::default(),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            PaletteMsg::Input(query) => {
+                self.query = query;
+                self.selected = 0;
+                true
+            }
+            PaletteMsg::KeyDown(e) => {
+                let count = self.results().len();
+                match e.key().as_str() {
+                    "ArrowDown" | "ArrowUp" if count > 0 => {
+                        e.prevent_default();
+                        self.selected = if e.key() == "ArrowDown" {
+                            (self.selected + 1) % count
+                        } else {
+                            (self.selected + count - 1) % count
+                        };
+                        true
+                    }
+                    "Enter" => {
+                        e.prevent_default();
+                        self.run(self.selected);
+                        false
+                    }
+                    "Escape" => {
+                        self.props.on_close.emit(());
+                        false
+                    }
+                    _ => false,
+                }
+            }
+            PaletteMsg::Run(index) => {
+                self.run(index);
+                false
+            }
+            PaletteMsg::Close => {
+                self.props.on_close.emit(());
+                false
+            }
+        }
+    }
+
+    fn rendered(&mut self, first_render: bool) {
+        if first_render {
+            if let Some(input) = self.input.cast::<web_sys::HtmlElement>() {
+                let _ = input.focus();
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        let results = self.results();
+        html! {
+            <div class="overlay" onclick=self.link.callback(|_| PaletteMsg::Close)>
+                <div class="overlay-panel palette shadow rounded" onclick=Callback::from(|e: MouseEvent| e.stop_propagation())>
+                    <input type="text" class="palette-input" placeholder="Go to, filter by tag, open an entry…"
+                        autocomplete="off" ref=self.input.clone() value=self.query.clone()
+                        oninput=self.link.callback(|e: InputData| PaletteMsg::Input(e.value))
+                        onkeydown=self.link.callback(PaletteMsg::KeyDown)/>
+                    <ul class="palette-results">
+                        { for results.iter().enumerate().map(|(index, item)| {
+                            let class = if index == self.selected {
+                                "palette-item palette-item-selected"
+                            } else {
+                                "palette-item"
+                            };
+                            html! {
+                                <li class=class onclick=self.link.callback(move |_| PaletteMsg::Run(index))>
+                                    <span class="search-suggestion-kind">{ item.kind }</span>
+                                    { &item.label }
+                                    { for item.hint.iter().map(|hint| html! { <span class="palette-hint">{ hint }</span> }) }
+                                </li>
+                            }
+                        }) }
+                    </ul>
+                </div>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use yew::services::storage::{Area, StorageService};
+
+// localStorage keys, prefixed so they don't collide with other apps on the
+// same origin (e.g. everything served by miniserve on localhost)
+
+pub const SERVER_URL: &str = "openmemex.server";
+pub const HIDE_COMPLETED: &str = "openmemex.hide_completed";
+pub const GALLERY_SORT: &str = "openmemex.sort";
+pub const SEARCH_HISTORY: &str = "openmemex.search_history";
+pub const SAVED_SEARCHES: &str = "openmemex.saved_searches";
+pub const KEYMAP: &str = "openmemex.keymap";
+pub const PALETTE_RECENT: &str = "openmemex.palette_recent";
+pub const GALLERY_VIEW: &str = "openmemex.view";
+pub const GRID_SIZE: &str = "openmemex.grid_size";
+pub const GALLERY_GROUP: &str = "openmemex.group";
+
+fn storage() -> Option<StorageService> {
+    match StorageService::new(Area::Local) {
+        Ok(storage) => Some(storage),
+        Err(error) => {
+            log::info!("local storage unavailable: {}", error);
+            None
+        }
+    }
+}
+
+pub fn get(key: &str) -> Option<String> {
+    let restored: Result<String, anyhow::Error> = storage()?.restore(key);
+    restored.ok()
+}
+
+pub fn set(key: &str, value: &str) {
+    if let Some(mut storage) = storage() {
+        storage.store(key, Ok(value.to_string()));
+    }
+}
+
+pub fn remove(key: &str) {
+    if let Some(mut storage) = storage() {
+        storage.remove(key);
+    }
+}
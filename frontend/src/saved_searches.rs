@@ -0,0 +1,188 @@
+use crate::prefs;
+use serde::{Deserialize, Serialize};
+use yew::prelude::*;
+use yew::Properties;
+use yew_router::agent::RouteRequest;
+use yew_router::prelude::*;
+
+// submitted searches kept for the history and autocomplete
+const HISTORY_LIMIT: usize = 20;
+// recent searches listed in the sidebar
+const SIDEBAR_HISTORY: usize = 5;
+
+fn load<T: for<'de> Deserialize<'de> + Default>(key: &str) -> T {
+    prefs::get(key)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn store<T: Serialize>(key: &str, value: &T) {
+    match serde_json::to_string(value) {
+        Ok(json) => prefs::set(key, &json),
+        Err(e) => log::info!("could not save {}: {}", key, e),
+    }
+}
+
+/// Submitted search texts, most recent first.
+pub fn history() -> Vec<String> {
+    load(prefs::SEARCH_HISTORY)
+}
+
+/// Put `search` at the front of the history and return the new history.
+pub fn remember(search: &str) -> Vec<String> {
+    let mut history = history();
+    history.retain(|s| s != search);
+    history.insert(0, search.to_string());
+    history.truncate(HISTORY_LIMIT);
+    store(prefs::SEARCH_HISTORY, &history);
+    history
+}
+
+/// A named gallery view. The url holds every filter, see `gallery_url`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedSearch {
+    pub name: String,
+    pub url: String,
+}
+
+pub enum SavedSearchesMsg {
+    NameEdit(String),
+    Save,
+    Open(String),
+    Remove(usize),
+}
+
+/// Sidebar with the saved searches ("smart collections") and the most
+/// recent searches.
+pub struct SavedSearches {
+    link: ComponentLink<Self>,
+    props: SavedProps,
+    saved: Vec<SavedSearch>,
+    name: String,
+    router: RouteAgentDispatcher<()>,
+}
+
+#[derive(Properties, PartialEq, Clone)]
+pub struct SavedProps {
+    /// gallery url of the current filters, saved as is
+    pub current_url: String,
+    pub history: Vec<String>,
+    /// run a search from the history
+    pub on_search: Callback<String>,
+}
+
+impl Component for SavedSearches {
+    type Message = SavedSearchesMsg;
+    type Properties = SavedProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            link,
+            props,
+            saved: load(prefs::SAVED_SEARCHES),
+            name: String::new(),
+            router: RouteAgentDispatcher::new(),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            SavedSearchesMsg::NameEdit(name) => {
+                self.name = name;
+                false
+            }
+            SavedSearchesMsg::Save => {
+                let name = self.name.trim().to_string();
+                if name.is_empty() {
+                    return false;
+                }
+                // saving under an existing name updates it
+                self.saved.retain(|s| s.name != name);
+                self.saved.push(SavedSearch {
+                    name,
+                    url: self.props.current_url.clone(),
+                });
+                store(prefs::SAVED_SEARCHES, &self.saved);
+                self.name.clear();
+                true
+            }
+            SavedSearchesMsg::Open(url) => {
+                // App follows route changes to gallery urls
+                self.router.send(RouteRequest::ChangeRoute(Route::from(url)));
+                false
+            }
+            SavedSearchesMsg::Remove(index) => {
+                if index < self.saved.len() {
+                    self.saved.remove(index);
+                    store(prefs::SAVED_SEARCHES, &self.saved);
+                }
+                true
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        let saved = self.saved.iter().enumerate().map(|(index, search)| {
+            let class = if search.url == self.props.current_url {
+                "saved-search saved-search-active"
+            } else {
+                "saved-search"
+            };
+            let url = search.url.clone();
+            html! {
+                <li class=class>
+                    <a href=search.url.clone() onclick=self.link.callback(move |e: MouseEvent| {
+                        e.prevent_default();
+                        SavedSearchesMsg::Open(url.clone())
+                    })>{ &search.name }</a>
+                    <button class="tag-chip-remove" title="Remove"
+                        onclick=self.link.callback(move |_| SavedSearchesMsg::Remove(index))>{ "✕" }</button>
+                </li>
+            }
+        });
+        let recent = self.props.history.iter().take(SIDEBAR_HISTORY).map(|search| {
+            let on_search = self.props.on_search.clone();
+            let text = search.clone();
+            html! {
+                <li class="recent-search" onclick=Callback::from(move |_| on_search.emit(text.clone()))>
+                    { search }
+                </li>
+            }
+        });
+        html! {
+            <div class="saved-searches">
+                <div class="saved-searches-title">{ "Saved searches" }</div>
+                <ul>{ for saved }</ul>
+                <div class="saved-search-add">
+                    <input type="text" placeholder="Name this view" value=self.name.clone()
+                        oninput=self.link.callback(|e: InputData| SavedSearchesMsg::NameEdit(e.value))
+                        onkeydown=self.link.batch_callback(|e: KeyboardEvent| {
+                            if e.key() == "Enter" { vec![SavedSearchesMsg::Save] } else { vec![] }
+                        })/>
+                    <button onclick=self.link.callback(|_| SavedSearchesMsg::Save)>{ "Save" }</button>
+                </div>
+                {
+                    if self.props.history.is_empty() {
+                        html! {}
+                    } else {
+                        html! {
+                            <>
+                                <div class="saved-searches-title">{ "Recent" }</div>
+                                <ul>{ for recent }</ul>
+                            </>
+                        }
+                    }
+                }
+            </div>
+        }
+    }
+}
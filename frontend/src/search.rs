@@ -0,0 +1,669 @@
+use crate::api::{Cache, CacheQuery};
+use crate::sort::domain;
+use crate::tags::TagSelection;
+use chrono::{Datelike, Duration, Months, NaiveDate, Weekday};
+use std::collections::HashSet;
+use std::fmt;
+
+/// Search box text split into its operators, e.g.
+/// `tag:rust -tag:news site:github.com after:2021-06 before:"last week" is:done "exact phrase"`.
+///
+/// `after:` includes the given day, month or year, `before:` excludes it.
+/// Anything that isn't an operator is a term every result has to contain.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    pub terms: Vec<String>,
+    pub not_terms: Vec<String>,
+    pub tags: Vec<String>,
+    pub not_tags: Vec<String>,
+    pub sites: Vec<String>,
+    pub not_sites: Vec<String>,
+    // first and last day included
+    pub after: Option<NaiveDate>,
+    pub before: Option<NaiveDate>,
+    // `is:done` / `is:todo`
+    pub done: Option<bool>,
+}
+
+/// Where and why the search text could not be parsed. `start..end` is a
+/// byte range of the text, for highlighting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchError {
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+struct Token<'a> {
+    start: usize,
+    end: usize,
+    negated: bool,
+    key: Option<&'a str>,
+    value: &'a str,
+}
+
+/// Whitespace separated tokens: `word`, `"a phrase"`, `key:value` or
+/// `key:"a value"`, each optionally prefixed with `-`. Quotes anywhere else
+/// are an error, so every term can be written back out (see `Display`).
+fn tokens(input: &str) -> Result<Vec<Token<'_>>, SearchError> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = input[pos..].find(|c: char| !c.is_whitespace()) {
+        let start = pos + offset;
+        let rest = &input[start..];
+        let negated = rest.starts_with('-') && rest[1..].starts_with(|c: char| !c.is_whitespace());
+        let body = start + negated as usize;
+        let rest = &input[body..];
+        let key_len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+        let key = if key_len > 0 && rest[key_len..].starts_with(':') {
+            Some(&rest[..key_len])
+        } else {
+            None
+        };
+        let value_start = key.map_or(body, |key| body + key.len() + 1);
+        let (value, end) = if input[value_start..].starts_with('"') {
+            let quoted = value_start + 1;
+            match input[quoted..].find('"') {
+                Some(close) => (&input[quoted..quoted + close], quoted + close + 1),
+                None => {
+                    return Err(SearchError {
+                        start,
+                        end: input.len(),
+                        message: "missing closing quote".to_string(),
+                    })
+                }
+            }
+        } else {
+            let len = input[value_start..]
+                .find(char::is_whitespace)
+                .unwrap_or(input.len() - value_start);
+            let end = value_start + len;
+            if input[value_start..end].contains('"') {
+                return Err(SearchError {
+                    start,
+                    end,
+                    message: "quotes only go around a whole phrase or value".to_string(),
+                });
+            }
+            (&input[value_start..end], end)
+        };
+        tokens.push(Token {
+            start,
+            end,
+            negated,
+            key,
+            value,
+        });
+        pos = end;
+    }
+    Ok(tokens)
+}
+
+/// Parse the search box text. Relative dates are resolved against `today`.
+pub fn parse(input: &str, today: NaiveDate) -> Result<SearchQuery, SearchError> {
+    let mut query = SearchQuery::default();
+    for token in tokens(input)? {
+        let error = |message: String| SearchError {
+            start: token.start,
+            end: token.end,
+            message,
+        };
+        let value = token.value.trim();
+        let key = token.key.map(|key| key.to_ascii_lowercase());
+        if value.is_empty() {
+            return Err(error(match &key {
+                Some(key) => format!("{}: needs a value", key),
+                None => "empty phrase".to_string(),
+            }));
+        }
+        let date = || resolve_date(value, today).ok_or_else(|| error(format!("\"{}\" is not a date", value)));
+        match (key.as_deref(), token.negated) {
+            (None, false) => query.terms.push(value.to_string()),
+            (None, true) => query.not_terms.push(value.to_string()),
+            (Some("tag"), false) => query.tags.push(value.to_string()),
+            (Some("tag"), true) => query.not_tags.push(value.to_string()),
+            (Some("site"), negated) => {
+                let site = site(value);
+                if site.is_empty() {
+                    return Err(error(format!("site:{} - expected a host like github.com", value)));
+                }
+                if negated {
+                    query.not_sites.push(site);
+                } else {
+                    query.sites.push(site);
+                }
+            }
+            (Some(key @ ("after" | "before")), true) => {
+                return Err(error(format!("{}: can't be negated", key)))
+            }
+            (Some("after"), false) => query.after = Some(date()?.0),
+            (Some("before"), false) => query.before = date()?.0.pred_opt(),
+            (Some("is"), negated) => {
+                let done = match value.to_ascii_lowercase().as_str() {
+                    "done" | "completed" => true,
+                    "todo" => false,
+                    _ => return Err(error(format!("is:{} - expected is:done or is:todo", value))),
+                };
+                query.done = Some(done != negated);
+            }
+            // not an operator, e.g. a url. `key:"a value"` loses its quotes,
+            // the whole term gets quoted when written back out
+            (Some(_), _) => {
+                let word = format!("{}:{}", token.key.unwrap_or_default(), value);
+                if token.negated {
+                    query.not_terms.push(word);
+                } else {
+                    query.terms.push(word);
+                }
+            }
+        }
+    }
+    Ok(query)
+}
+
+fn site(value: &str) -> String {
+    let value = value.to_lowercase();
+    let host = value.split_once("://").map_or(value.as_str(), |(_, rest)| rest);
+    let host = host.split('/').next().unwrap_or(host);
+    host.trim_start_matches("www.").to_string()
+}
+
+fn month_range(year: i32, month: u32) -> Option<(NaiveDate, NaiveDate)> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+    Some((first, last))
+}
+
+fn year_range(year: i32) -> Option<(NaiveDate, NaiveDate)> {
+    Some((NaiveDate::from_ymd_opt(year, 1, 1)?, NaiveDate::from_ymd_opt(year, 12, 31)?))
+}
+
+fn weekday(name: &str) -> Option<Weekday> {
+    let day = match name {
+        "monday" | "mon" => Weekday::Mon,
+        "tuesday" | "tue" => Weekday::Tue,
+        "wednesday" | "wed" => Weekday::Wed,
+        "thursday" | "thu" => Weekday::Thu,
+        "friday" | "fri" => Weekday::Fri,
+        "saturday" | "sat" => Weekday::Sat,
+        "sunday" | "sun" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(day)
+}
+
+/// First and last day of a date, month (`2021-06`), year (`2021`) or one of
+/// `today`, `yesterday`, `this/last week|month|year`, `N days|weeks|months|years ago`
+/// and weekday names (the most recent one before today). Weeks start on Monday.
+pub fn resolve_date(value: &str, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    let value = value.trim().to_lowercase();
+    if let Ok(day) = NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+        return Some((day, day));
+    }
+    if let Some((year, month)) = value.split_once('-') {
+        if let (Ok(year), Ok(month)) = (year.parse(), month.parse()) {
+            return month_range(year, month);
+        }
+    }
+    if value.len() == 4 {
+        if let Ok(year) = value.parse() {
+            return year_range(year);
+        }
+    }
+    let words: Vec<&str> = value.split_whitespace().collect();
+    let week_start = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let day = |d: NaiveDate| Some((d, d));
+    match words.as_slice() {
+        ["today"] => day(today),
+        ["yesterday"] => day(today.pred_opt()?),
+        ["this", "week"] => Some((week_start, week_start + Duration::days(6))),
+        ["last", "week"] => Some((week_start - Duration::days(7), week_start - Duration::days(1))),
+        ["this", "month"] => month_range(today.year(), today.month()),
+        ["last", "month"] => {
+            let last = today.checked_sub_months(Months::new(1))?;
+            month_range(last.year(), last.month())
+        }
+        ["this", "year"] => year_range(today.year()),
+        ["last", "year"] => year_range(today.year() - 1),
+        [count, unit, "ago"] => {
+            let count: u32 = count.parse().ok()?;
+            match unit.trim_end_matches('s') {
+                "day" => day(today - Duration::days(count as i64)),
+                "week" => day(today - Duration::weeks(count as i64)),
+                "month" => day(today.checked_sub_months(Months::new(count))?),
+                "year" => day(today.checked_sub_months(Months::new(count.checked_mul(12)?))?),
+                _ => None,
+            }
+        }
+        [name] | ["last", name] => {
+            let target = weekday(name)?;
+            let back = (today.weekday().num_days_from_monday() + 7 - target.num_days_from_monday() - 1) % 7 + 1;
+            day(today - Duration::days(back as i64))
+        }
+        _ => None,
+    }
+}
+
+/// `value` quoted if the tokenizer would otherwise split it or read it as
+/// an operator. Parsed values never contain quotes themselves.
+pub fn quoted(value: &str) -> String {
+    if value.contains(char::is_whitespace) || value.contains(':') || value.starts_with('-') {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for SearchQuery {
+    /// Parses back to the same query.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = Vec::new();
+        parts.extend(self.terms.iter().map(|t| quoted(t)));
+        parts.extend(self.not_terms.iter().map(|t| format!("-{}", quoted(t))));
+        parts.extend(self.tags.iter().map(|t| format!("tag:{}", quoted(t))));
+        parts.extend(self.not_tags.iter().map(|t| format!("-tag:{}", quoted(t))));
+        parts.extend(self.sites.iter().map(|s| format!("site:{}", quoted(s))));
+        parts.extend(self.not_sites.iter().map(|s| format!("-site:{}", quoted(s))));
+        if let Some(after) = self.after {
+            parts.push(format!("after:{}", after));
+        }
+        if let Some(before) = self.before.and_then(|before| before.succ_opt()) {
+            parts.push(format!("before:{}", before));
+        }
+        match self.done {
+            Some(true) => parts.push("is:done".to_string()),
+            Some(false) => parts.push("is:todo".to_string()),
+            None => {}
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+impl SearchQuery {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether `apply` would move anything into the gallery filters.
+    pub fn has_facets(&self) -> bool {
+        !self.tags.is_empty()
+            || !self.not_tags.is_empty()
+            || self.after.is_some()
+            || self.before.is_some()
+            || self.done == Some(false)
+    }
+
+    /// Term sent to `/search`, which matches a single substring. The longest
+    /// one narrows things down the most, the others are checked in `matches`.
+    pub fn server_term(&self) -> Option<&str> {
+        self.terms.iter().max_by_key(|t| t.len()).map(String::as_str)
+    }
+
+    /// Move the operators the gallery already has filters for into `query`
+    /// and `tags`, returning what is left for `/search` and `matches`.
+    pub fn apply(mut self, query: &mut CacheQuery, tags: &mut TagSelection) -> Option<SearchQuery> {
+        for tag in self.tags.drain(..) {
+            tags.exclude.retain(|t| *t != tag);
+            if !tags.include.contains(&tag) {
+                tags.include.push(tag);
+            }
+        }
+        for tag in self.not_tags.drain(..) {
+            tags.include.retain(|t| *t != tag);
+            if !tags.exclude.contains(&tag) {
+                tags.exclude.push(tag);
+            }
+        }
+        query.tags = tags.include.clone();
+        if let Some(after) = self.after.take() {
+            query.start_date = Some(after);
+        }
+        if let Some(before) = self.before.take() {
+            query.end_date = Some(before);
+        }
+        if self.done == Some(false) {
+            query.hidecompleted = Some(true);
+            self.done = None;
+        }
+        if self.is_empty() {
+            None
+        } else {
+            Some(self)
+        }
+    }
+
+    /// Client-side part of the query. The `server_term` is left out, `/search`
+    /// also matches it against tags which aren't loaded here.
+    pub fn matches(&self, entry: &Cache, completed: &HashSet<i32>) -> bool {
+        let text = format!(
+            "{} {}",
+            entry.content.as_deref().unwrap_or(""),
+            entry.url.as_deref().unwrap_or("")
+        )
+        .to_lowercase();
+        let server_term = self.server_term();
+        let contains = |term: &String| text.contains(&term.to_lowercase());
+        let on_site = |site: &String| {
+            domain(entry).is_some_and(|d| d == *site || d.ends_with(&format!(".{}", site)))
+        };
+        self.terms.iter().filter(|t| Some(t.as_str()) != server_term).all(contains)
+            && !self.not_terms.iter().any(contains)
+            && (self.sites.is_empty() || self.sites.iter().any(on_site))
+            && !self.not_sites.iter().any(on_site)
+            && self.done.is_none_or(|done| completed.contains(&entry.entry_id) == done)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a Wednesday
+    fn today() -> NaiveDate {
+        date(2021, 6, 16)
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn parsed(input: &str) -> SearchQuery {
+        parse(input, today()).unwrap_or_else(|e| panic!("{:?} did not parse: {:?}", input, e))
+    }
+
+    fn error(input: &str) -> (usize, usize, String) {
+        let error = parse(input, today()).expect_err(input);
+        (error.start, error.end, error.message)
+    }
+
+    fn entry(entry_id: i32, content: &str, url: Option<&str>) -> Cache {
+        Cache {
+            time: "12:00:00".to_string(),
+            entry_id,
+            content: Some(content.to_string()),
+            date: "2021-06-16".to_string(),
+            url: url.map(str::to_string),
+            thumbnail_file: None,
+        }
+    }
+
+    #[test]
+    fn tokens_split_on_whitespace_outside_quotes() {
+        let input = r#" rust -news tag:"machine learning" -"a b"c  x:y "#;
+        let tokens = tokens(input).unwrap();
+        let summary: Vec<_> = tokens
+            .iter()
+            .map(|t| (&input[t.start..t.end], t.negated, t.key, t.value))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("rust", false, None, "rust"),
+                ("-news", true, None, "news"),
+                (r#"tag:"machine learning""#, false, Some("tag"), "machine learning"),
+                (r#"-"a b""#, true, None, "a b"),
+                ("c", false, None, "c"),
+                ("x:y", false, Some("x"), "y"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens_lone_dash_is_a_word() {
+        let input = "- a-b";
+        let tokens = tokens(input).unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert!(!tokens[0].negated);
+        assert_eq!(tokens[0].value, "-");
+        assert_eq!(tokens[1].value, "a-b");
+    }
+
+    #[test]
+    fn parse_terms_and_phrases() {
+        let query = parsed(r#"rust "exact phrase" -news -"bad phrase""#);
+        assert_eq!(query.terms, strings(&["rust", "exact phrase"]));
+        assert_eq!(query.not_terms, strings(&["news", "bad phrase"]));
+    }
+
+    #[test]
+    fn parse_tags() {
+        let query = parsed(r#"tag:rust TAG:"machine learning" -tag:news"#);
+        assert_eq!(query.tags, strings(&["rust", "machine learning"]));
+        assert_eq!(query.not_tags, strings(&["news"]));
+    }
+
+    #[test]
+    fn parse_sites() {
+        let query = parsed("site:https://www.GitHub.com/rust-lang -site:news.ycombinator.com");
+        assert_eq!(query.sites, strings(&["github.com"]));
+        assert_eq!(query.not_sites, strings(&["news.ycombinator.com"]));
+    }
+
+    #[test]
+    fn parse_after_and_before() {
+        let query = parsed("after:2021-06 before:2021");
+        assert_eq!(query.after, Some(date(2021, 6, 1)));
+        // before excludes the whole year
+        assert_eq!(query.before, Some(date(2020, 12, 31)));
+
+        let query = parsed(r#"after:"last week" before:today"#);
+        assert_eq!(query.after, Some(date(2021, 6, 7)));
+        assert_eq!(query.before, Some(date(2021, 6, 15)));
+    }
+
+    #[test]
+    fn parse_is() {
+        assert_eq!(parsed("is:done").done, Some(true));
+        assert_eq!(parsed("is:Completed").done, Some(true));
+        assert_eq!(parsed("is:todo").done, Some(false));
+        assert_eq!(parsed("-is:done").done, Some(false));
+        assert_eq!(parsed("-is:todo").done, Some(true));
+    }
+
+    #[test]
+    fn parse_unknown_key_is_a_term() {
+        let query = parsed(r#"https://example.com foo:"bar baz" -Foo:x"#);
+        assert_eq!(query.terms, strings(&["https://example.com", "foo:bar baz"]));
+        assert_eq!(query.not_terms, strings(&["Foo:x"]));
+    }
+
+    #[test]
+    fn parse_empty() {
+        assert!(parsed("").is_empty());
+        assert!(parsed("   ").is_empty());
+    }
+
+    #[test]
+    fn parse_error_spans() {
+        let missing = "a -tag:\"b c";
+        assert_eq!(error(missing), (2, missing.len(), "missing closing quote".to_string()));
+        assert_eq!(error("a tag: b"), (2, 6, "tag: needs a value".to_string()));
+        assert_eq!(error(r#"a tag:"" b"#), (2, 8, "tag: needs a value".to_string()));
+        assert_eq!(error(r#"a "  " b"#), (2, 6, "empty phrase".to_string()));
+        assert_eq!(error("a ab\"c b"), (2, 6, "quotes only go around a whole phrase or value".to_string()));
+        assert_eq!(error("a tag:x\"y"), (2, 9, "quotes only go around a whole phrase or value".to_string()));
+        assert_eq!(error("a after:someday"), (2, 15, "\"someday\" is not a date".to_string()));
+        assert_eq!(error("a -before:2021"), (2, 14, "before: can't be negated".to_string()));
+        assert_eq!(error("a -after:2021"), (2, 13, "after: can't be negated".to_string()));
+        assert_eq!(error("a is:maybe"), (2, 10, "is:maybe - expected is:done or is:todo".to_string()));
+        assert_eq!(error("a site:/"), (2, 8, "site:/ - expected a host like github.com".to_string()));
+    }
+
+    #[test]
+    fn resolve_absolute_dates() {
+        let resolve = |value| resolve_date(value, today());
+        assert_eq!(resolve("2021-03-04"), Some((date(2021, 3, 4), date(2021, 3, 4))));
+        assert_eq!(resolve("2021-02"), Some((date(2021, 2, 1), date(2021, 2, 28))));
+        assert_eq!(resolve("2020-12"), Some((date(2020, 12, 1), date(2020, 12, 31))));
+        assert_eq!(resolve("2020"), Some((date(2020, 1, 1), date(2020, 12, 31))));
+        assert_eq!(resolve("2021-13"), None);
+        assert_eq!(resolve("2021-02-30"), None);
+        assert_eq!(resolve("soon"), None);
+    }
+
+    #[test]
+    fn resolve_relative_dates() {
+        let resolve = |value| resolve_date(value, today());
+        let day = |d| Some((d, d));
+        assert_eq!(resolve("today"), day(today()));
+        assert_eq!(resolve("Yesterday"), day(date(2021, 6, 15)));
+        assert_eq!(resolve("this week"), Some((date(2021, 6, 14), date(2021, 6, 20))));
+        assert_eq!(resolve("last week"), Some((date(2021, 6, 7), date(2021, 6, 13))));
+        assert_eq!(resolve("this month"), Some((date(2021, 6, 1), date(2021, 6, 30))));
+        assert_eq!(resolve("last month"), Some((date(2021, 5, 1), date(2021, 5, 31))));
+        assert_eq!(resolve("this year"), Some((date(2021, 1, 1), date(2021, 12, 31))));
+        assert_eq!(resolve("last year"), Some((date(2020, 1, 1), date(2020, 12, 31))));
+        assert_eq!(
+            resolve_date("last month", date(2021, 1, 10)),
+            Some((date(2020, 12, 1), date(2020, 12, 31)))
+        );
+    }
+
+    #[test]
+    fn resolve_ago() {
+        let resolve = |value| resolve_date(value, today());
+        let day = |d| Some((d, d));
+        assert_eq!(resolve("1 day ago"), day(date(2021, 6, 15)));
+        assert_eq!(resolve("3 days ago"), day(date(2021, 6, 13)));
+        assert_eq!(resolve("2 weeks ago"), day(date(2021, 6, 2)));
+        assert_eq!(resolve("1 month ago"), day(date(2021, 5, 16)));
+        assert_eq!(resolve("2 years ago"), day(date(2019, 6, 16)));
+        assert_eq!(resolve("many days ago"), None);
+        assert_eq!(resolve("3 fortnights ago"), None);
+    }
+
+    #[test]
+    fn resolve_weekdays() {
+        let resolve = |value| resolve_date(value, today());
+        let day = |d| Some((d, d));
+        // the most recent one before today
+        assert_eq!(resolve("monday"), day(date(2021, 6, 14)));
+        assert_eq!(resolve("tue"), day(date(2021, 6, 15)));
+        assert_eq!(resolve("wednesday"), day(date(2021, 6, 9)));
+        assert_eq!(resolve("last friday"), day(date(2021, 6, 11)));
+        assert_eq!(resolve("Sun"), day(date(2021, 6, 13)));
+        assert_eq!(resolve("last funday"), None);
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let cases = [
+            "",
+            "rust",
+            r#"foo:"bar baz""#,
+            r#"-foo:"bar baz""#,
+            r#""foo:bar" http://example.com/a?b=c"#,
+            r#""-dash" -"-dash" - --x"#,
+            r#"tag:"machine learning" -tag:-x tag:a:b"#,
+            r#"site:"localhost:8080" -site:https://www.example.com/x"#,
+            r#"after:2021 before:2021-06 is:todo"#,
+            r#"after:"3 days ago" before:"last week" -is:todo"#,
+            r#"   "  padded  "   "#,
+            r#""a b"c"#,
+        ];
+        for input in cases {
+            let query = parsed(input);
+            let text = query.to_string();
+            assert_eq!(parse(&text, today()), Ok(query), "{:?} printed as {:?}", input, text);
+        }
+    }
+
+    #[test]
+    fn display_round_trip_generated() {
+        let pieces = [
+            "a", "B", "-", "x:y", "tag:", "-tag:", "site:", "-site:", "after:", "before:", "is:", "foo:", "\"",
+            " ", "  ", "done", "todo", "2021", "-06", "github.com", "://", "/", "www.", "é", "\t",
+        ];
+        // small linear congruential generator, the same inputs every run
+        let mut state: u64 = 1;
+        let mut next = |n: usize| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) as usize % n
+        };
+        let mut parsed_count = 0;
+        for _ in 0..20000 {
+            let len = next(8);
+            let input: String = (0..len).map(|_| pieces[next(pieces.len())]).collect();
+            if let Ok(query) = parse(&input, today()) {
+                parsed_count += 1;
+                let text = query.to_string();
+                assert_eq!(parse(&text, today()), Ok(query), "{:?} printed as {:?}", input, text);
+            }
+        }
+        assert!(parsed_count > 1000, "only {} inputs parsed", parsed_count);
+    }
+
+    #[test]
+    fn matches_terms_and_sites() {
+        let completed = HashSet::new();
+        let rust = entry(1, "The Rust Book", Some("https://doc.rust-lang.org/book"));
+        let blog = entry(2, "Rust blog", Some("https://www.blog.rust-lang.org"));
+        let note = entry(3, "a note about rust", None);
+
+        // the longest term is left to the server
+        let query = parsed("rust-lang book");
+        assert_eq!(query.server_term(), Some("rust-lang"));
+        assert!(query.matches(&rust, &completed));
+        assert!(!query.matches(&blog, &completed));
+
+        let query = parsed("rust -BLOG");
+        assert!(query.matches(&rust, &completed));
+        assert!(!query.matches(&blog, &completed));
+        assert!(query.matches(&note, &completed));
+
+        // subdomains match, other domains ending the same way don't
+        let query = parsed("site:rust-lang.org");
+        assert!(query.matches(&rust, &completed));
+        assert!(query.matches(&blog, &completed));
+        assert!(!query.matches(&note, &completed));
+        assert!(!parsed("site:lang.org").matches(&rust, &completed));
+
+        let query = parsed("-site:blog.rust-lang.org");
+        assert!(query.matches(&rust, &completed));
+        assert!(!query.matches(&blog, &completed));
+        assert!(query.matches(&note, &completed));
+    }
+
+    #[test]
+    fn matches_done() {
+        let completed: HashSet<i32> = vec![1].into_iter().collect();
+        let done = entry(1, "done", None);
+        let todo = entry(2, "todo", None);
+        assert!(parsed("is:done").matches(&done, &completed));
+        assert!(!parsed("is:done").matches(&todo, &completed));
+        assert!(parsed("is:todo").matches(&todo, &completed));
+        assert!(!parsed("is:todo").matches(&done, &completed));
+        assert!(parsed("").matches(&done, &completed));
+    }
+
+    #[test]
+    fn apply_moves_facets_into_the_filters() {
+        let mut query = CacheQuery::default();
+        let mut tags = TagSelection {
+            include: strings(&["news"]),
+            exclude: strings(&["rust"]),
+            ..TagSelection::default()
+        };
+        let rest = parsed("rust tag:rust -tag:news after:2021 before:2022 is:todo").apply(&mut query, &mut tags);
+        assert_eq!(tags.include, strings(&["rust"]));
+        assert_eq!(tags.exclude, strings(&["news"]));
+        assert_eq!(query.tags, strings(&["rust"]));
+        assert_eq!(query.start_date, Some(date(2021, 1, 1)));
+        assert_eq!(query.end_date, Some(date(2021, 12, 31)));
+        assert_eq!(query.hidecompleted, Some(true));
+        assert_eq!(rest.map(|q| q.terms), Some(strings(&["rust"])));
+
+        let mut query = CacheQuery::default();
+        assert_eq!(parsed("tag:rust").apply(&mut query, &mut TagSelection::default()), None);
+    }
+}
@@ -0,0 +1,244 @@
+use crate::search::quoted;
+use yew::prelude::*;
+use yew::Properties;
+
+const SUGGESTION_LIMIT: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Suggestion {
+    label: String,
+    kind: &'static str,
+    // search box text after picking it
+    text: String,
+    // past searches run right away, completed operators wait for more
+    submit: bool,
+}
+
+/// Past searches containing `value`, then tag and domain completions of the
+/// last word (`tag:`, `-tag:`, `site:` or a bare prefix).
+fn suggestions(value: &str, history: &[String], tags: &[String], domains: &[String]) -> Vec<Suggestion> {
+    let lower = value.trim().to_lowercase();
+    let mut found: Vec<Suggestion> = history
+        .iter()
+        .filter(|s| *s != value.trim() && s.to_lowercase().contains(&lower))
+        .map(|s| Suggestion {
+            label: s.clone(),
+            kind: "recent",
+            text: s.clone(),
+            submit: true,
+        })
+        .collect();
+    if lower.is_empty() {
+        found.truncate(SUGGESTION_LIMIT);
+        return found;
+    }
+
+    let start = value.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+    let (before, word) = value.split_at(start);
+    let (negated, word) = match word.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", word),
+    };
+    let complete = |kind: &'static str, operator: &str, candidates: &[String], partial: &str| {
+        let partial = partial.to_lowercase();
+        candidates
+            .iter()
+            .filter(|c| c.to_lowercase().starts_with(&partial) && c.to_lowercase() != partial)
+            .map(|c| Suggestion {
+                label: format!("{}{}:{}", negated, operator, c),
+                kind,
+                text: format!("{}{}{}:{} ", before, negated, operator, quoted(c)),
+                submit: false,
+            })
+            .collect::<Vec<_>>()
+    };
+    if let Some(partial) = word.strip_prefix("tag:") {
+        found.extend(complete("tag", "tag", tags, partial));
+    } else if let Some(partial) = word.strip_prefix("site:") {
+        found.extend(complete("site", "site", domains, partial));
+    } else if word.chars().count() >= 2 && !word.contains(':') {
+        found.extend(complete("tag", "tag", tags, word));
+        found.extend(complete("site", "site", domains, word));
+    }
+    found.truncate(SUGGESTION_LIMIT);
+    found
+}
+
+pub enum SearchBoxMsg {
+    Input(String),
+    KeyDown(KeyboardEvent),
+    Pick(usize),
+    Open(bool),
+}
+
+/// Search input with an autocomplete dropdown. Arrow keys move through the
+/// suggestions, Enter or Tab picks one and Escape closes the list.
+pub struct SearchBox {
+    link: ComponentLink<Self>,
+    props: SearchBoxProps,
+    // what is typed, ahead of `props.value` which only changes on submit
+    value: String,
+    open: bool,
+    selected: Option<usize>,
+}
+
+#[derive(Properties, PartialEq, Clone)]
+pub struct SearchBoxProps {
+    pub value: String,
+    #[prop_or_default]
+    pub invalid: bool,
+    pub history: Vec<String>,
+    pub tags: Vec<String>,
+    pub domains: Vec<String>,
+    pub on_input: Callback<String>,
+    pub on_submit: Callback<()>,
+}
+
+impl SearchBox {
+    fn suggestions(&self) -> Vec<Suggestion> {
+        suggestions(&self.value, &self.props.history, &self.props.tags, &self.props.domains)
+    }
+
+    fn pick(&mut self, suggestion: Suggestion) {
+        self.value = suggestion.text.clone();
+        self.props.on_input.emit(suggestion.text);
+        self.selected = None;
+        if suggestion.submit {
+            self.open = false;
+            self.props.on_submit.emit(());
+        }
+    }
+}
+
+impl Component for SearchBox {
+    type Message = SearchBoxMsg;
+    type Properties = SearchBoxProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            link,
+            value: props.value.clone(),
+            props,
+            open: false,
+            selected: None,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props == props {
+            return false;
+        }
+        if self.props.value != props.value {
+            self.value = props.value.clone();
+        }
+        self.props = props;
+        true
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            SearchBoxMsg::Input(value) => {
+                self.value = value.clone();
+                self.open = true;
+                self.selected = None;
+                self.props.on_input.emit(value);
+                true
+            }
+            SearchBoxMsg::KeyDown(e) => {
+                let count = self.suggestions().len();
+                match e.key().as_str() {
+                    "ArrowDown" | "ArrowUp" if count > 0 => {
+                        e.prevent_default();
+                        let down = e.key() == "ArrowDown";
+                        self.selected = Some(match (self.selected, down) {
+                            (None, true) => 0,
+                            (None, false) => count - 1,
+                            (Some(i), true) => (i + 1) % count,
+                            (Some(i), false) => (i + count - 1) % count,
+                        });
+                        self.open = true;
+                        true
+                    }
+                    "Enter" | "Tab" if self.open && self.selected.is_some() => {
+                        e.prevent_default();
+                        let picked = self.selected.and_then(|i| self.suggestions().into_iter().nth(i));
+                        if let Some(suggestion) = picked {
+                            self.pick(suggestion);
+                        }
+                        true
+                    }
+                    "Enter" => {
+                        self.open = false;
+                        self.props.on_submit.emit(());
+                        true
+                    }
+                    "Escape" => {
+                        self.open = false;
+                        self.selected = None;
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            SearchBoxMsg::Pick(index) => {
+                if let Some(suggestion) = self.suggestions().into_iter().nth(index) {
+                    self.pick(suggestion);
+                }
+                true
+            }
+            SearchBoxMsg::Open(open) => {
+                self.open = open;
+                self.selected = None;
+                true
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        let suggestions = if self.open { self.suggestions() } else { Vec::new() };
+        let class = if self.props.invalid {
+            "search-input search-input-error shadow-sm p-3 mb-5 bg-white rounded"
+        } else {
+            "search-input shadow-sm p-3 mb-5 bg-white rounded"
+        };
+        html! {
+            <div class="search-box">
+                <input type="text" class=class placeholder="Search" accesskey="/" autocomplete="off"
+                    title="tag:rust -tag:news site:github.com after:2021-06 before:\"last week\" is:done \"exact phrase\""
+                    value=self.value.clone()
+                    oninput=self.link.callback(|e: InputData| SearchBoxMsg::Input(e.value))
+                    onkeydown=self.link.callback(SearchBoxMsg::KeyDown)
+                    onfocus=self.link.callback(|_| SearchBoxMsg::Open(true))
+                    onblur=self.link.callback(|_| SearchBoxMsg::Open(false))
+                />
+                {
+                    if suggestions.is_empty() {
+                        html! {}
+                    } else {
+                        html! {
+                            <ul class="search-suggestions">
+                                { for suggestions.iter().enumerate().map(|(index, s)| {
+                                    let class = if self.selected == Some(index) {
+                                        "search-suggestion search-suggestion-selected"
+                                    } else {
+                                        "search-suggestion"
+                                    };
+                                    // mousedown comes before the input's blur closes the list
+                                    html! {
+                                        <li class=class onmousedown=self.link.callback(move |e: MouseEvent| {
+                                            e.prevent_default();
+                                            SearchBoxMsg::Pick(index)
+                                        })>
+                                            <span class="search-suggestion-kind">{ s.kind }</span>
+                                            { &s.label }
+                                        </li>
+                                    }
+                                }) }
+                            </ul>
+                        }
+                    }
+                }
+            </div>
+        }
+    }
+}
@@ -1,56 +1,154 @@
+use crate::api::*;
+use crate::keymap::*;
+use std::collections::HashMap;
+use yew::agent::Dispatched;
 use yew::prelude::*;
+use yew::utils::window;
 use yew::Properties;
 
-pub enum SettingsMsg {}
-
-pub enum FieldType {
-    IntField,
-}
-
-pub struct IntFieldData {
-    pub min: i32,
-    pub max: i32,
-}
-
-pub struct Setting {
-    pub field: String,
-    pub field_type: FieldType,
+pub enum SettingsMsg {
+    UrlEdit(String),
+    Save,
+    Reset,
+    KeysEdit(Action, String),
+    SaveKeys,
+    ResetKeys,
 }
 
 pub struct Settings {
     pub link: ComponentLink<Self>,
+    server_url: String,
+    // comma separated bindings as typed, per action
+    keys: HashMap<Action, String>,
 }
 
 #[derive(Clone, Properties)]
 pub struct Props {}
 
-    /*
-impl SettingComponent for IntFieldData {
-    fn render(&mut self) -> {
+fn keys_text(keymap: &Keymap) -> HashMap<Action, String> {
+    Action::ALL
+        .iter()
+        .map(|action| (*action, keymap.keys(*action).join(", ")))
+        .collect()
+}
 
+// every component builds its ApiClient on create, reloading is the simplest
+// way to make them all pick up a new server location
+fn reload() {
+    if let Err(error) = window().location().reload() {
+        log::info!("reload failed: {:?}", error);
     }
 }
-    */
+
 impl Component for Settings {
     type Message = SettingsMsg;
     type Properties = Props;
 
-    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        Self { link: link }
+    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            link,
+            server_url: server_base_url(),
+            keys: keys_text(&Keymap::load()),
+        }
     }
 
-    fn change(&mut self, props: Self::Properties) -> bool {
+    fn change(&mut self, _props: Self::Properties) -> bool {
         false
     }
 
     fn update(&mut self, msg: Self::Message) -> bool {
-        false
+        match msg {
+            SettingsMsg::UrlEdit(url) => {
+                self.server_url = url;
+                false
+            }
+            SettingsMsg::Save => {
+                log::info!("saving server url {:?}", self.server_url);
+                if self.server_url.trim().is_empty() {
+                    set_server_base_url(None);
+                } else {
+                    set_server_base_url(Some(&self.server_url));
+                }
+                reload();
+                false
+            }
+            SettingsMsg::Reset => {
+                log::info!("clearing server url override");
+                set_server_base_url(None);
+                reload();
+                false
+            }
+            SettingsMsg::KeysEdit(action, keys) => {
+                self.keys.insert(action, keys);
+                false
+            }
+            SettingsMsg::SaveKeys => {
+                let mut keymap = Keymap::load();
+                for (action, text) in &self.keys {
+                    let keys = text
+                        .split(',')
+                        .map(|k| k.split_whitespace().collect::<Vec<_>>().join(" "))
+                        .filter(|k| !k.is_empty())
+                        .collect();
+                    keymap.set_keys(*action, keys);
+                }
+                keymap.save();
+                KeyBindings::dispatcher().send(KeyBindingsInput::Reload);
+                self.keys = keys_text(&keymap);
+                true
+            }
+            SettingsMsg::ResetKeys => {
+                Keymap::reset();
+                KeyBindings::dispatcher().send(KeyBindingsInput::Reload);
+                self.keys = keys_text(&Keymap::default());
+                true
+            }
+        }
     }
 
     fn view(&self) -> Html {
         html! {
-            <div>
-            {"Settings"}
+            <div class="settings">
+                <label for="server-url">{ "Server URL" }</label>
+                <input type="text" id="server-url" class="settings-input shadow-sm p-3 mb-3 bg-white rounded"
+                    placeholder="https://example.org/openmemex"
+                    value = { self.server_url.clone() }
+                    oninput = { self.link.callback(|e: InputData| SettingsMsg::UrlEdit(e.value)) }
+                />
+                <div>
+                    <button class="settings-button shadow-sm p-2 bg-white rounded"
+                        onclick = { self.link.callback(|_| SettingsMsg::Save) }>{ "Save" }</button>
+                    <button class="settings-button shadow-sm p-2 bg-white rounded"
+                        onclick = { self.link.callback(|_| SettingsMsg::Reset) }>{ "Reset" }</button>
+                </div>
+                <p class="settings-hint">
+                    { "Leave empty to use the server the page was loaded from, or set <meta name=\"openmemex-server\"> in index.html." }
+                </p>
+                <h5>{ "Keyboard shortcuts" }</h5>
+                <table class="key-help">
+                    { for Action::ALL.iter().map(|action| {
+                        let action = *action;
+                        html! {
+                            <tr>
+                                <td>{ action.describe() }</td>
+                                <td>
+                                    <input type="text" class="settings-input shadow-sm p-2 bg-white rounded"
+                                        value=self.keys.get(&action).cloned().unwrap_or_default()
+                                        oninput=self.link.callback(move |e: InputData| SettingsMsg::KeysEdit(action, e.value))/>
+                                </td>
+                            </tr>
+                        }
+                    }) }
+                </table>
+                <div>
+                    <button class="settings-button shadow-sm p-2 bg-white rounded"
+                        onclick = { self.link.callback(|_| SettingsMsg::SaveKeys) }>{ "Save shortcuts" }</button>
+                    <button class="settings-button shadow-sm p-2 bg-white rounded"
+                        onclick = { self.link.callback(|_| SettingsMsg::ResetKeys) }>{ "Reset shortcuts" }</button>
+                </div>
+                <p class="settings-hint">
+                    { "Separate keys with commas, a sequence with spaces (\"g g\"). Key names are KeyboardEvent.key values such as ArrowDown or Escape." }
+                </p>
             </div>
         }
     }
@@ -0,0 +1,273 @@
+use crate::api::{Cache, CacheQuery, SortBy, SortDir};
+use std::cmp::Ordering;
+use url::Url;
+
+/// What the gallery is ordered by. `/all/cache` pages in this order and
+/// `apply` keeps the loaded pages (and search results) in it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Date,
+    Domain,
+    Title,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 3] = [SortKey::Date, SortKey::Domain, SortKey::Title];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortKey::Date => "Date",
+            SortKey::Domain => "Domain",
+            SortKey::Title => "Title",
+        }
+    }
+
+    fn as_param(&self) -> &'static str {
+        match self {
+            SortKey::Date => "date",
+            SortKey::Domain => "domain",
+            SortKey::Title => "title",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GallerySort {
+    pub key: SortKey,
+    pub dir: SortDir,
+}
+
+impl Default for GallerySort {
+    /// newest first, like the server
+    fn default() -> Self {
+        Self {
+            key: SortKey::Date,
+            dir: SortDir::Rev,
+        }
+    }
+}
+
+fn dir_param(dir: SortDir) -> &'static str {
+    match dir {
+        SortDir::Fwd => "asc",
+        SortDir::Rev => "desc",
+    }
+}
+
+impl GallerySort {
+    /// Clicking the active key flips the direction, another key starts
+    /// ascending (dates newest first).
+    pub fn toggle(&self, key: SortKey) -> Self {
+        let dir = match (self.key == key, self.dir, key) {
+            (true, SortDir::Fwd, _) => SortDir::Rev,
+            (true, SortDir::Rev, _) => SortDir::Fwd,
+            (false, _, SortKey::Date) => SortDir::Rev,
+            (false, _, _) => SortDir::Fwd,
+        };
+        Self { key, dir }
+    }
+
+    /// Sets `sort` and `sortdir` of `/all/cache` to this order.
+    pub fn set_query(&self, query: &mut CacheQuery) {
+        query.sort = Some(match self.key {
+            SortKey::Date => SortBy::Time,
+            SortKey::Domain => SortBy::Url,
+            SortKey::Title => SortBy::Title,
+        });
+        query.sortdir = Some(self.dir);
+    }
+
+    pub fn params(&self) -> (&'static str, &'static str) {
+        (self.key.as_param(), dir_param(self.dir))
+    }
+
+    /// Reverse of `params`, `None` for anything unknown.
+    pub fn from_params(key: &str, dir: &str) -> Option<Self> {
+        let key = SortKey::ALL.iter().copied().find(|k| k.as_param() == key)?;
+        let dir = match dir {
+            "asc" => SortDir::Fwd,
+            "desc" => SortDir::Rev,
+            _ => return None,
+        };
+        Some(Self { key, dir })
+    }
+
+    /// Stable sort of `entries`. Entries without a domain (plain notes) or
+    /// title stay at the end in either direction.
+    pub fn apply(&self, entries: &mut [Cache]) {
+        let by_date = |a: &Cache, b: &Cache| (&a.date, &a.time).cmp(&(&b.date, &b.time));
+        entries.sort_by(|a, b| {
+            let order = match self.key {
+                SortKey::Date => by_date(a, b),
+                _ => match (sort_text(self.key, a), sort_text(self.key, b)) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (Some(_), None) => return Ordering::Less,
+                    (None, Some(_)) => return Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                },
+            };
+            match self.dir {
+                SortDir::Fwd => order,
+                SortDir::Rev => order.reverse(),
+            }
+        });
+    }
+}
+
+/// Host of the entry's url without a leading `www.`.
+pub fn domain(entry: &Cache) -> Option<String> {
+    let url = Url::parse(entry.url.as_ref()?).ok()?;
+    let host = url.host_str()?;
+    Some(host.strip_prefix("www.").unwrap_or(host).to_string())
+}
+
+/// The host expression of `cacheOrder`: whatever follows the first `://` up
+/// to the next `/`, lowercased and without a leading `www.`. Unlike `domain`
+/// it keeps ports and doesn't need a valid url, as sqlite doesn't either.
+fn sort_host(url: &str) -> Option<String> {
+    let (_, rest) = url.split_once("://")?;
+    let host = rest.split('/').next().unwrap_or(rest).to_ascii_lowercase();
+    Some(host.strip_prefix("www.").map(str::to_string).unwrap_or(host))
+}
+
+/// What domain and title order compare, the same as `cacheOrder` in
+/// `shared/DB.hs` (sqlite's `LOWER` and `TRIM` only touch ascii and spaces).
+pub fn sort_text(key: SortKey, entry: &Cache) -> Option<String> {
+    match key {
+        SortKey::Date => None,
+        SortKey::Domain => entry.url.as_deref().and_then(sort_host),
+        SortKey::Title => entry.content.as_ref().map(|c| c.trim_matches(' ').to_ascii_lowercase()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(entry_id: i32, date: &str, url: Option<&str>, title: Option<&str>) -> Cache {
+        Cache {
+            time: "12:00:00".to_string(),
+            entry_id,
+            content: title.map(str::to_string),
+            date: date.to_string(),
+            url: url.map(str::to_string),
+            thumbnail_file: None,
+        }
+    }
+
+    fn with_url(url: &str) -> Cache {
+        entry(1, "2021-06-16", Some(url), None)
+    }
+
+    fn ids(entries: &[Cache]) -> Vec<i32> {
+        entries.iter().map(|e| e.entry_id).collect()
+    }
+
+    #[test]
+    fn domain_strips_www() {
+        let domain = |url| domain(&with_url(url));
+        assert_eq!(domain("https://www.GitHub.com/rust-lang"), Some("github.com".to_string()));
+        assert_eq!(domain("http://blog.rust-lang.org"), Some("blog.rust-lang.org".to_string()));
+        assert_eq!(domain("http://localhost:8080/x"), Some("localhost".to_string()));
+        assert_eq!(domain("note without url"), None);
+        assert_eq!(super::domain(&entry(1, "2021-06-16", None, None)), None);
+    }
+
+    #[test]
+    fn domain_sort_text_matches_cache_order() {
+        // expected values of the `hostExpr` in `cacheOrder`
+        let cases = [
+            ("https://www.GitHub.com/rust-lang", Some("github.com")),
+            ("HTTP://WWW.Example.org", Some("example.org")),
+            ("http://blog.rust-lang.org/2021/", Some("blog.rust-lang.org")),
+            ("http://localhost:8080/x", Some("localhost:8080")),
+            ("https://user@host.com/", Some("user@host.com")),
+            ("https://a.com?q=1", Some("a.com?q=1")),
+            ("https://www.", Some("")),
+            ("file:///home/notes.txt", Some("")),
+            ("https://wwwx.com", Some("wwwx.com")),
+            ("https://Ünïcode.com", Some("Ünïcode.com")),
+            ("mailto:someone@example.com", None),
+            ("just a note", None),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(
+                sort_text(SortKey::Domain, &with_url(url)).as_deref(),
+                expected,
+                "{}",
+                url
+            );
+        }
+        assert_eq!(sort_text(SortKey::Domain, &entry(1, "2021-06-16", None, None)), None);
+    }
+
+    #[test]
+    fn title_sort_text_trims_spaces() {
+        let title = |title| sort_text(SortKey::Title, &entry(1, "2021-06-16", None, Some(title)));
+        assert_eq!(title("  The Rust Book "), Some("the rust book".to_string()));
+        assert_eq!(title("\tTabs stay"), Some("\ttabs stay".to_string()));
+        assert_eq!(sort_text(SortKey::Title, &entry(1, "2021-06-16", None, None)), None);
+        assert_eq!(sort_text(SortKey::Date, &with_url("https://a.com")), None);
+    }
+
+    #[test]
+    fn apply_keeps_missing_keys_last() {
+        // newest first as loaded, ties keep that order like `cacheOrder`
+        let mut entries = vec![
+            entry(4, "2021-06-04", Some("https://b.com/x"), Some("gamma")),
+            entry(3, "2021-06-03", Some("https://www.a.com"), Some("Alpha")),
+            entry(2, "2021-06-02", None, None),
+            entry(1, "2021-06-01", Some("https://b.com"), Some("beta")),
+        ];
+        let sort = |key, dir, entries: &mut Vec<Cache>| GallerySort { key, dir }.apply(entries);
+
+        sort(SortKey::Domain, SortDir::Fwd, &mut entries);
+        assert_eq!(ids(&entries), vec![3, 4, 1, 2]);
+        sort(SortKey::Domain, SortDir::Rev, &mut entries);
+        assert_eq!(ids(&entries), vec![4, 1, 3, 2]);
+        sort(SortKey::Title, SortDir::Fwd, &mut entries);
+        assert_eq!(ids(&entries), vec![3, 1, 4, 2]);
+        sort(SortKey::Title, SortDir::Rev, &mut entries);
+        assert_eq!(ids(&entries), vec![4, 1, 3, 2]);
+        sort(SortKey::Date, SortDir::Rev, &mut entries);
+        assert_eq!(ids(&entries), vec![4, 3, 2, 1]);
+        sort(SortKey::Date, SortDir::Fwd, &mut entries);
+        assert_eq!(ids(&entries), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn toggle() {
+        let date = GallerySort::default();
+        let domain = date.toggle(SortKey::Domain);
+        assert_eq!((domain.key, domain.dir), (SortKey::Domain, SortDir::Fwd));
+        assert_eq!(domain.toggle(SortKey::Domain).dir, SortDir::Rev);
+        assert_eq!(domain.toggle(SortKey::Date), date);
+        assert_eq!(date.toggle(SortKey::Date).dir, SortDir::Fwd);
+    }
+
+    #[test]
+    fn params_round_trip() {
+        for key in SortKey::ALL {
+            for dir in [SortDir::Fwd, SortDir::Rev] {
+                let sort = GallerySort { key, dir };
+                let (key, dir) = sort.params();
+                assert_eq!(GallerySort::from_params(key, dir), Some(sort));
+            }
+        }
+        assert_eq!(GallerySort::from_params("size", "asc"), None);
+        assert_eq!(GallerySort::from_params("title", "up"), None);
+    }
+
+    #[test]
+    fn set_query() {
+        let mut query = CacheQuery::default();
+        GallerySort {
+            key: SortKey::Domain,
+            dir: SortDir::Rev,
+        }
+        .set_query(&mut query);
+        assert_eq!((query.sort, query.sortdir), (Some(SortBy::Url), Some(SortDir::Rev)));
+        GallerySort::default().set_query(&mut query);
+        assert_eq!((query.sort, query.sortdir), (Some(SortBy::Time), Some(SortDir::Rev)));
+    }
+}
@@ -20,12 +20,12 @@ fn grid() -> Html {
                 .step_by(20)
                 .map(move |y| {
                     let suffix = format!("{:?}_{:?}", x, y);
-                    let id = format!("circle-{:?}", suffix);
-                    let id_pound = format!("#circle-{:?}", suffix);
-                    let x_id_anim = format!("x-circle-anim-{:?}", suffix);
-                    let y_id_anim = format!("y-circle-anim-{:?}", suffix);
-                    let x_max = x + 50;
-                    let y_max = y + 50;
+                    let _id = format!("circle-{:?}", suffix);
+                    let _id_pound = format!("#circle-{:?}", suffix);
+                    let _x_id_anim = format!("x-circle-anim-{:?}", suffix);
+                    let _y_id_anim = format!("y-circle-anim-{:?}", suffix);
+                    let _x_max = x + 50;
+                    let _y_max = y + 50;
                     html! {
                         <>
                             /*
@@ -72,15 +72,15 @@ impl Component for Space {
     type Message = SpaceMsg;
     type Properties = Props;
 
-    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        Self { link: link }
+    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self { link }
     }
 
-    fn change(&mut self, props: Self::Properties) -> bool {
+    fn change(&mut self, _props: Self::Properties) -> bool {
         false
     }
 
-    fn update(&mut self, msg: Self::Message) -> bool {
+    fn update(&mut self, _msg: Self::Message) -> bool {
         false
     }
 
@@ -1,26 +1,130 @@
 use std::collections::HashSet;
 use yew::prelude::*;
+use yew::utils::window;
 use yew::Properties;
 
+/// Comma separated tags, blanks dropped.
+pub fn parse_tags(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Ask for comma separated tags to add to `title`. `None` when cancelled or
+/// nothing was entered.
+pub fn prompt_tags(title: &str) -> Option<Vec<String>> {
+    let message = format!("Add tags to \"{}\" (comma separated)", title);
+    let tags = parse_tags(&window().prompt_with_message(&message).ok()??);
+    if tags.is_empty() {
+        None
+    } else {
+        Some(tags)
+    }
+}
+
+/// How included tags combine. `/all/cache` matches entries with any of the
+/// `tag` params, `All` is narrowed down client-side.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TagMode {
+    #[default]
+    Any,
+    All,
+}
+
+/// Tags picked in the tag list: a plain click selects a single tag,
+/// Ctrl-click adds or removes included tags and Shift-click excluded ones.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TagSelection {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub mode: TagMode,
+}
+
+impl TagSelection {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Plain click: only `tag`, or nothing if it was the only selected tag.
+    pub fn select_only(&self, tag: &str) -> Self {
+        if self.include == [tag] && self.exclude.is_empty() {
+            Self::default()
+        } else {
+            Self {
+                include: vec![tag.to_string()],
+                mode: self.mode,
+                ..Self::default()
+            }
+        }
+    }
+
+    /// Ctrl/Shift-click: add `tag` to the included or excluded tags, or drop
+    /// it if it is already in that set.
+    pub fn toggle(&self, tag: &str, exclude: bool) -> Self {
+        let mut next = self.clone();
+        let (set, other) = if exclude {
+            (&mut next.exclude, &mut next.include)
+        } else {
+            (&mut next.include, &mut next.exclude)
+        };
+        if set.iter().any(|t| t == tag) {
+            set.retain(|t| t != tag);
+        } else {
+            set.push(tag.to_string());
+            other.retain(|t| t != tag);
+        }
+        next
+    }
+
+    pub fn remove(&self, tag: &str) -> Self {
+        let mut next = self.clone();
+        next.include.retain(|t| t != tag);
+        next.exclude.retain(|t| t != tag);
+        next
+    }
+
+    /// Whether `matches` has to run on the entries `/all/cache` returns for
+    /// `include`. The server only does "any of", without exclusions.
+    pub fn needs_client_filter(&self) -> bool {
+        !self.exclude.is_empty() || (self.mode == TagMode::All && self.include.len() > 1)
+    }
+
+    /// Every tag `matches` needs to know about.
+    pub fn filter_tags(&self) -> Vec<String> {
+        self.include.iter().chain(&self.exclude).cloned().collect()
+    }
+
+    pub fn matches(&self, entry_tags: &HashSet<String>) -> bool {
+        let included = match self.mode {
+            TagMode::Any => self.include.is_empty() || self.include.iter().any(|t| entry_tags.contains(t)),
+            TagMode::All => self.include.iter().all(|t| entry_tags.contains(t)),
+        };
+        included && !self.exclude.iter().any(|t| entry_tags.contains(t))
+    }
+}
+
 pub enum TagsMsg {
-    TagClick(MouseEvent, String),
-    TagHover(MouseEvent, String),
-    TagExit(MouseEvent, String),
+    Click(MouseEvent, String),
+    Hover(MouseEvent, String),
+    Exit(MouseEvent, String),
 }
 
 #[derive(Debug)]
 pub struct Tags {
     pub link: ComponentLink<Self>,
     tags: Option<Vec<String>>,
-    pub tag_click_callback: Callback<Option<String>>,
-    pub selected: Option<HashSet<String>>,
+    pub tag_click_callback: Callback<TagSelection>,
+    pub selected: TagSelection,
     pub hovered: Option<String>,
 }
 
 #[derive(Properties, PartialEq, Clone)]
 pub struct Props {
     pub tags: Option<Vec<String>>,
-    pub tag_click_callback: Callback<Option<String>>,
+    pub selected: TagSelection,
+    pub tag_click_callback: Callback<TagSelection>,
 }
 
 impl Tags {}
@@ -32,10 +136,10 @@ impl Component for Tags {
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         log::info!("Creating tags component");
         Self {
-            link: link,
+            link,
             tags: props.tags,
             tag_click_callback: props.tag_click_callback,
-            selected: None,
+            selected: props.selected,
             hovered: None,
         }
     }
@@ -43,6 +147,7 @@ impl Component for Tags {
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
         self.tags = props.tags;
         self.tag_click_callback = props.tag_click_callback;
+        self.selected = props.selected;
         true
     }
 
@@ -50,25 +155,24 @@ impl Component for Tags {
         use TagsMsg::*;
         log::info!("tags update");
         match msg {
-            TagClick(_m, tag_name) => {
+            Click(m, tag_name) => {
                 log::info!("tag click event");
-                let hs: HashSet<String> = vec![tag_name.clone()].into_iter().collect();
-                let curr: HashSet<String> = self.selected.clone().unwrap_or(HashSet::new());
-                if curr.contains(&tag_name) {
-                    self.selected = None;
-                    self.tag_click_callback.emit(None);
+                let selection = if m.shift_key() {
+                    self.selected.toggle(&tag_name, true)
+                } else if m.ctrl_key() || m.meta_key() {
+                    self.selected.toggle(&tag_name, false)
                 } else {
-                    self.selected = Some(hs);
-                    self.tag_click_callback.emit(Some(tag_name));
-                }
-                true
+                    self.selected.select_only(&tag_name)
+                };
+                self.tag_click_callback.emit(selection);
+                false
             }
-            TagHover(_m, tag_name) => {
+            Hover(_m, tag_name) => {
                 log::info!("tag hover event");
                 self.hovered = Some(tag_name);
                 true
             }
-            TagExit(_m, tag_name) => {
+            Exit(_m, _tag_name) => {
                 log::info!("tag exit event");
                 self.hovered = None;
                 true
@@ -81,33 +185,35 @@ impl Component for Tags {
         let exist_tags = self.tags.as_ref().unwrap_or(empty_vec);
         let callback = |item: String| {
             self.link
-                .callback(move |m| TagsMsg::TagClick(m, item.to_string()))
+                .callback(move |m| TagsMsg::Click(m, item.to_string()))
         };
         let hover_callback = |item: String| {
             self.link
-                .callback(move |m| TagsMsg::TagHover(m, item.to_string()))
+                .callback(move |m| TagsMsg::Hover(m, item.to_string()))
         };
         let exit_callback = |item: String| {
             self.link
-                .callback(move |m| TagsMsg::TagExit(m, item.to_string()))
+                .callback(move |m| TagsMsg::Exit(m, item.to_string()))
         };
 
         let hovered = self.hovered.clone().unwrap_or("".to_string());
 
         html! {
                 <div class="topic-tags">
-                    <div>
+                    <div title="Ctrl-click to add a tag, Shift-click to exclude one">
                         { for exist_tags.iter().map((move |item: &String| {
-                            let hs = self.selected.clone().unwrap_or(HashSet::new());
                             let mut style = "topic-tag";
                             if hovered.eq(item) {
                                 style = "topic-tag-hover";
                             }
-                            if hs.contains(item) {
+                            if self.selected.include.contains(item) {
                                 style = "topic-tag-selected";
                             }
+                            if self.selected.exclude.contains(item) {
+                                style = "topic-tag-excluded";
+                            }
                             html! {
-                                <div class= { style } onclick=callback(item.clone()).clone() 
+                                <div class= { style } onclick=callback(item.clone()).clone()
                                     onmouseover=hover_callback(item.clone()).clone()
                                     onmouseleave=exit_callback(item.clone()).clone()>
                                 { item.clone() }
@@ -120,3 +226,182 @@ impl Component for Tags {
         }
     }
 }
+
+pub enum TagChipsMsg {
+    Remove(String),
+    ToggleMode,
+}
+
+/// Selected tags shown as removable chips, with the any/all switch once more
+/// than one tag is included. Part of the `Facets` bar.
+pub struct TagChips {
+    link: ComponentLink<Self>,
+    props: ChipsProps,
+}
+
+#[derive(Properties, PartialEq, Clone)]
+pub struct ChipsProps {
+    pub selected: TagSelection,
+    pub on_change: Callback<TagSelection>,
+}
+
+impl Component for TagChips {
+    type Message = TagChipsMsg;
+    type Properties = ChipsProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self { link, props }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        let selected = &self.props.selected;
+        let next = match msg {
+            TagChipsMsg::Remove(tag) => selected.remove(&tag),
+            TagChipsMsg::ToggleMode => TagSelection {
+                mode: match selected.mode {
+                    TagMode::Any => TagMode::All,
+                    TagMode::All => TagMode::Any,
+                },
+                ..selected.clone()
+            },
+        };
+        self.props.on_change.emit(next);
+        false
+    }
+
+    fn view(&self) -> Html {
+        let selected = &self.props.selected;
+        if selected.is_empty() {
+            return html! {};
+        }
+        let chip = |tag: &String, class: &'static str, label: String| {
+            let tag = tag.clone();
+            html! {
+                <span class=format!("tag-chip {}", class)>
+                    { label }
+                    <button class="tag-chip-remove"
+                        onclick=self.link.callback(move |_| TagChipsMsg::Remove(tag.clone()))>{ "✕" }</button>
+                </span>
+            }
+        };
+        let mode = match selected.mode {
+            TagMode::Any => "any of",
+            TagMode::All => "all of",
+        };
+        html! {
+            <span class="tag-chips">
+                {
+                    if selected.include.len() > 1 {
+                        html! {
+                            <button class="tag-chip-mode" title="Switch between any and all of the tags"
+                                onclick=self.link.callback(|_| TagChipsMsg::ToggleMode)>{ mode }</button>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                { for selected.include.iter().map(|tag| chip(tag, "tag-chip-include", tag.clone())) }
+                { for selected.exclude.iter().map(|tag| chip(tag, "tag-chip-exclude", format!("not {}", tag))) }
+            </span>
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn selection(include: &[&str], exclude: &[&str], mode: TagMode) -> TagSelection {
+        TagSelection {
+            include: strings(include),
+            exclude: strings(exclude),
+            mode,
+        }
+    }
+
+    fn entry_tags(tags: &[&str]) -> HashSet<String> {
+        tags.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_tags_drops_blanks() {
+        assert_eq!(parse_tags(" rust, ,machine learning ,,"), strings(&["rust", "machine learning"]));
+        assert!(parse_tags("").is_empty());
+    }
+
+    #[test]
+    fn select_only() {
+        let picked = selection(&["a", "b"], &["c"], TagMode::All).select_only("a");
+        assert_eq!(picked, selection(&["a"], &[], TagMode::All));
+        // clicking the only selected tag again clears the selection
+        assert_eq!(picked.select_only("a"), TagSelection::default());
+        assert_eq!(picked.select_only("b"), selection(&["b"], &[], TagMode::All));
+    }
+
+    #[test]
+    fn toggle_include_and_exclude() {
+        let empty = TagSelection::default();
+        let included = empty.toggle("a", false).toggle("b", false);
+        assert_eq!(included, selection(&["a", "b"], &[], TagMode::Any));
+        assert_eq!(included.toggle("a", false), selection(&["b"], &[], TagMode::Any));
+
+        // excluding an included tag moves it over, and back again
+        let excluded = included.toggle("a", true);
+        assert_eq!(excluded, selection(&["b"], &["a"], TagMode::Any));
+        assert_eq!(excluded.toggle("a", false), selection(&["b", "a"], &[], TagMode::Any));
+        assert_eq!(excluded.toggle("a", true), selection(&["b"], &[], TagMode::Any));
+    }
+
+    #[test]
+    fn remove() {
+        let tags = selection(&["a", "b"], &["c"], TagMode::Any);
+        assert_eq!(tags.remove("a"), selection(&["b"], &["c"], TagMode::Any));
+        assert_eq!(tags.remove("c"), selection(&["a", "b"], &[], TagMode::Any));
+        assert_eq!(tags.remove("x"), tags);
+    }
+
+    #[test]
+    fn needs_client_filter() {
+        assert!(!TagSelection::default().needs_client_filter());
+        assert!(!selection(&["a", "b"], &[], TagMode::Any).needs_client_filter());
+        assert!(!selection(&["a"], &[], TagMode::All).needs_client_filter());
+        assert!(selection(&["a", "b"], &[], TagMode::All).needs_client_filter());
+        assert!(selection(&[], &["c"], TagMode::Any).needs_client_filter());
+        assert_eq!(selection(&["a"], &["c"], TagMode::Any).filter_tags(), strings(&["a", "c"]));
+    }
+
+    #[test]
+    fn matches_any() {
+        let tags = selection(&["a", "b"], &["c"], TagMode::Any);
+        assert!(tags.matches(&entry_tags(&["a"])));
+        assert!(tags.matches(&entry_tags(&["b", "d"])));
+        assert!(!tags.matches(&entry_tags(&["d"])));
+        assert!(!tags.matches(&entry_tags(&["a", "c"])));
+        // nothing included: everything but the excluded tags
+        let tags = selection(&[], &["c"], TagMode::Any);
+        assert!(tags.matches(&entry_tags(&[])));
+        assert!(!tags.matches(&entry_tags(&["c"])));
+    }
+
+    #[test]
+    fn matches_all() {
+        let tags = selection(&["a", "b"], &["c"], TagMode::All);
+        assert!(tags.matches(&entry_tags(&["a", "b"])));
+        assert!(tags.matches(&entry_tags(&["a", "b", "d"])));
+        assert!(!tags.matches(&entry_tags(&["a"])));
+        assert!(!tags.matches(&entry_tags(&["a", "b", "c"])));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::card::host_simplify;
+use url::Url;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, IntersectionObserver, IntersectionObserverEntry, IntersectionObserverInit};
+use yew::prelude::*;
+use yew::Properties;
+
+// start loading thumbnails this far outside the viewport
+const ROOT_MARGIN: &str = "300px";
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Stand-in for a missing or broken thumbnail: the simplified host of `url`
+/// (or "Note") on a background colored by it, as an svg data url.
+pub fn placeholder(url: Option<&str>) -> String {
+    let label = url
+        .and_then(|url| Url::parse(url).ok())
+        .and_then(|url| url.host_str().map(host_simplify))
+        .unwrap_or_else(|| "Note".to_string());
+    let hue = label.bytes().fold(0u32, |hash, b| hash.wrapping_mul(31).wrapping_add(b as u32)) % 360;
+    let svg = format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="300" viewBox="0 0 400 300">"#,
+            r#"<rect width="400" height="300" fill="hsl({hue}, 45%, 88%)"/>"#,
+            r#"<text x="200" y="150" font-family="sans-serif" font-size="32" fill="hsl({hue}, 40%, 30%)" "#,
+            r#"text-anchor="middle" dominant-baseline="middle">{label}</text></svg>"#
+        ),
+        hue = hue,
+        label = escape_xml(&label)
+    );
+    format!("data:image/svg+xml;charset=utf-8,{}", urlencoding::encode(&svg))
+}
+
+pub enum ThumbnailMsg {
+    Visible,
+    Failed,
+}
+
+/// Card image that only loads once it comes near the viewport, showing
+/// `placeholder` until then and when there is no thumbnail or it fails.
+pub struct Thumbnail {
+    link: ComponentLink<Self>,
+    props: ThumbnailProps,
+    visible: bool,
+    failed: bool,
+    node: NodeRef,
+    observer: Option<IntersectionObserver>,
+    // kept alive for as long as the observer may call it
+    _on_intersect: Option<Closure<dyn Fn(js_sys::Array)>>,
+}
+
+#[derive(Properties, PartialEq, Clone)]
+pub struct ThumbnailProps {
+    /// thumbnail url, empty when the entry has none
+    pub src: String,
+    /// the entry url, for the placeholder
+    #[prop_or_default]
+    pub url: Option<String>,
+    #[prop_or_default]
+    pub class: String,
+    #[prop_or_default]
+    pub style: String,
+}
+
+impl Thumbnail {
+    fn observe(&mut self) {
+        let element = match self.node.cast::<Element>() {
+            Some(element) => element,
+            None => return,
+        };
+        let visible = self.link.callback(|_| ThumbnailMsg::Visible);
+        let on_intersect = Closure::wrap(Box::new(move |entries: js_sys::Array| {
+            let intersecting = entries
+                .iter()
+                .filter_map(|entry| entry.dyn_into::<IntersectionObserverEntry>().ok())
+                .any(|entry| entry.is_intersecting());
+            if intersecting {
+                visible.emit(());
+            }
+        }) as Box<dyn Fn(js_sys::Array)>);
+        let options = IntersectionObserverInit::new();
+        options.set_root_margin(ROOT_MARGIN);
+        match IntersectionObserver::new_with_options(on_intersect.as_ref().unchecked_ref(), &options) {
+            Ok(observer) => {
+                observer.observe(&element);
+                self.observer = Some(observer);
+                self._on_intersect = Some(on_intersect);
+            }
+            Err(e) => {
+                // no IntersectionObserver, load right away
+                log::info!("could not observe thumbnail: {:?}", e);
+                self.link.send_message(ThumbnailMsg::Visible);
+            }
+        }
+    }
+
+    fn disconnect(&mut self) {
+        if let Some(observer) = self.observer.take() {
+            observer.disconnect();
+        }
+        self._on_intersect = None;
+    }
+}
+
+impl Component for Thumbnail {
+    type Message = ThumbnailMsg;
+    type Properties = ThumbnailProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            link,
+            props,
+            visible: false,
+            failed: false,
+            node: NodeRef::default(),
+            observer: None,
+            _on_intersect: None,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            if self.props.src != props.src {
+                self.failed = false;
+            }
+            self.props = props;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            ThumbnailMsg::Visible => {
+                self.disconnect();
+                let changed = !self.visible;
+                self.visible = true;
+                changed
+            }
+            ThumbnailMsg::Failed => {
+                log::info!("could not load thumbnail {}", self.props.src);
+                self.failed = true;
+                true
+            }
+        }
+    }
+
+    fn rendered(&mut self, first_render: bool) {
+        if first_render {
+            self.observe();
+        }
+    }
+
+    fn destroy(&mut self) {
+        self.disconnect();
+    }
+
+    fn view(&self) -> Html {
+        let props = &self.props;
+        if self.visible && !self.failed && !props.src.is_empty() {
+            html! {
+                <img ref=self.node.clone() src=props.src.clone() class=props.class.clone() style=props.style.clone()
+                    onerror=self.link.callback(|_| ThumbnailMsg::Failed)/>
+            }
+        } else {
+            html! {
+                <img ref=self.node.clone() src=placeholder(props.url.as_deref())
+                    class=format!("{} thumbnail-placeholder", props.class) style=props.style.clone()/>
+            }
+        }
+    }
+}
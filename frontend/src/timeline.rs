@@ -1,19 +1,14 @@
 use crate::api::*;
+use crate::notifications::*;
 use yew::Properties;
-use yew::services::fetch::{FetchService, FetchTask, Request, Response};
-use yew::{
-    format::{Json, Nothing},
-    prelude::*,
-    utils::host,
-};
+use yew::agent::{Dispatched, Dispatcher};
+use yew::prelude::*;
 
 use chrono::*;
 
-use wasm_bindgen::prelude::*;
-
 pub enum TimelineMsg {
     GetTimeline,
-    ReceiveTimeline(Result<Vec<Timestamp>, anyhow::Error>),
+    ReceiveTimeline(ApiResult<Vec<Timestamp>>),
     Hover(MouseEvent, String),
     Click(MouseEvent),
 }
@@ -27,10 +22,12 @@ pub struct Timeline {
     pub time_coord: i32,
     pub utc_range: (i64, i64),
     pub timeline_callback: Callback<Option<(NaiveDateTime, NaiveDateTime)>>,
-    task: Option<FetchTask>,
+    api: ApiClient,
+    task: Option<ApiTask>,
+    notifier: Dispatcher<NotificationBus>,
 }
 
-const window_half_duration: i64 = 3;
+const WINDOW_HALF_DURATION: i64 = 3;
 
 #[derive(Clone, Properties)]
 pub struct Props {
@@ -52,58 +49,58 @@ fn get_time(&mut self) -> (NaiveDateTime, NaiveDateTime) {
             .round() as i64;
         let dt = NaiveDateTime::from_timestamp(utc, 0);
         // if adding / subtractiong the time window fails, clamp at the dt value
-        let window_max = dt.checked_add_signed(Duration::days(window_half_duration))
+        let window_max = dt.checked_add_signed(Duration::days(WINDOW_HALF_DURATION))
                            .unwrap_or(dt);
-        let window_min = dt.checked_sub_signed(Duration::days(window_half_duration))
+        let window_min = dt.checked_sub_signed(Duration::days(WINDOW_HALF_DURATION))
                            .unwrap_or(dt);
         (window_min, window_max)
 }
 */
 
+fn utc_to_naive(utc: i64) -> NaiveDateTime {
+    DateTime::from_timestamp(utc, 0)
+        .map(|dt| dt.naive_utc())
+        .unwrap_or_default()
+}
+
 impl Component for Timeline {
     type Message = TimelineMsg;
     type Properties = Props;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-
-        let server = host().unwrap();
-        let cb = link.callback_once(|_: String| TimelineMsg::GetTimeline);
-        cb.emit("".to_string());
+        link.send_message(TimelineMsg::GetTimeline);
         Self {
-            link: link,
+            link,
             time_coord: 0,
             events: [].to_vec(),
             time_window: None,
             locations: [].to_vec(),
             utc_range: (0, 0),
             timeline_callback: props.timeline_callback,
+            api: ApiClient::new(),
             task: None,
+            notifier: NotificationBus::dispatcher(),
         }
     }
 
-    fn change(&mut self, props: Self::Properties) -> bool {
+    fn change(&mut self, _props: Self::Properties) -> bool {
         false
     }
 
     fn update(&mut self, msg: Self::Message) -> bool {
         use TimelineMsg::*;
-        let server = host().unwrap();
         log::info!("update");
         match msg {
             GetTimeline => {
-                let query = format!("http://{}/all/timestamps/", server.to_string());
-                log::info!("submitting tl request: {}", query);
-                let request = Request::get(&query)
-                    .body(Nothing)
-                    .expect("Could not build request.");
-                let callback = self.link.callback_once(
-                    |response: Response<Json<Result<Vec<Timestamp>, anyhow::Error>>>| {
-                        let Json(data) = response.into_body();
-                        TimelineMsg::ReceiveTimeline(data)
-                    });
-                let task = FetchService::fetch(request, callback).expect("failed to start request");
-                self.task = Some(task);
                 log::info!("submitting timeline request");
+                let callback = self.link.callback(TimelineMsg::ReceiveTimeline);
+                match self.api.all_timestamps(callback) {
+                    Ok(task) => self.task = Some(task),
+                    Err(error) => {
+                        let retry = self.link.callback(|_| TimelineMsg::GetTimeline);
+                        notify_error(&mut self.notifier, "Could not load timeline", &error, retry)
+                    }
+                }
                 false
             }
             ReceiveTimeline(response) => {
@@ -134,8 +131,11 @@ impl Component for Timeline {
                     Err(error) => {
                         log::info!("timeline error:");
                         log::info!("{:?}", &error.to_string());
+                        let retry = self.link.callback(|_| TimelineMsg::GetTimeline);
+                        notify_error(&mut self.notifier, "Could not load timeline", &error, retry);
                     }
                 }
+                self.task = None;
                 true
             }
             Hover(m, _s) => { 
@@ -145,17 +145,16 @@ impl Component for Timeline {
                 let document = window.document().expect("should have a document on window");
                 let timeline = document.get_element_by_id("timeline-svg").expect("get element by id shouldn't fail");
                 let width = timeline.client_width();
-                let height = timeline.client_height();
                 let frac_position: f32 = (self.time_coord as f32) / (width as f32);
                 let utc: i64 = 
                     (self.utc_range.0 as f32 
                      + frac_position * (self.utc_range.1 - self.utc_range.0) as f32)
                     .round() as i64;
-                let dt = NaiveDateTime::from_timestamp(utc, 0);
+                let dt = utc_to_naive(utc);
                 // if adding / subtractiong the time window fails, clamp at the dt value
-                let window_max = dt.checked_add_signed(Duration::days(window_half_duration))
+                let window_max = dt.checked_add_signed(Duration::days(WINDOW_HALF_DURATION))
                                    .unwrap_or(dt);
-                let window_min = dt.checked_sub_signed(Duration::days(window_half_duration))
+                let window_min = dt.checked_sub_signed(Duration::days(WINDOW_HALF_DURATION))
                                    .unwrap_or(dt);
                 self.time_window = Some((window_min, window_max));
 
@@ -176,11 +175,11 @@ impl Component for Timeline {
                     (self.utc_range.0 as f32 
                      + frac_position * (self.utc_range.1 - self.utc_range.0) as f32)
                     .round() as i64;
-                let dt = NaiveDateTime::from_timestamp(utc, 0);
+                let dt = utc_to_naive(utc);
                 // if adding / subtractiong the time window fails, clamp at the dt value
-                let window_max = dt.checked_add_signed(Duration::days(window_half_duration))
+                let window_max = dt.checked_add_signed(Duration::days(WINDOW_HALF_DURATION))
                                    .unwrap_or(dt);
-                let window_min = dt.checked_sub_signed(Duration::days(window_half_duration))
+                let window_min = dt.checked_sub_signed(Duration::days(WINDOW_HALF_DURATION))
                                    .unwrap_or(dt);
                 self.time_window = Some((window_min, window_max));
 
@@ -204,9 +203,7 @@ impl Component for Timeline {
         let hover_callback = |input: String| {
             self.link.callback(move |m| TimelineMsg::Hover(m, input.clone()))
         };
-        let click_callback = || {
-            self.link.callback(move |m| TimelineMsg::Click(m))
-        };
+        let click_callback = || self.link.callback(TimelineMsg::Click);
 
         let stroke = "stroke:rgb(0,0,0,0.1); stroke-width:2";
         let cursor_bg = "stroke:rgb(0,0,0,0.1); stroke-width:12";
@@ -236,7 +233,7 @@ impl Component for Timeline {
                     <text x="0%"  y="80%" style=text_style>
                     {
                         match &self.time_window {
-                            Some((start, end)) => {
+                            Some((start, _end)) => {
                                 start.format("%Y %b %e").to_string()
                             }
                             None => { "".to_string() }
@@ -0,0 +1,363 @@
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use frontend::api::{Cache, EntryTag, HitSource, SearchHit, SortBy, SortDir, Timestamp};
+use frontend::sort::{GallerySort, SortKey};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+/// Row of the `entries` table, served by `/all/entries`.
+#[derive(Serialize, Debug, Clone)]
+pub struct Entry {
+    #[serde(rename = "entryID")]
+    pub entry_id: i32,
+    pub date: String,
+    pub time: String,
+    pub content: String,
+}
+
+/// Parameters of `/all/cache`, see `AllCacheAPI` in `server/Main.hs`.
+#[derive(Debug, Default)]
+pub struct CacheParams {
+    pub sort: Option<SortBy>,
+    pub sortdir: Option<SortDir>,
+    pub tags: Vec<String>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+    pub hide_completed: bool,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+/// In-memory stand-in for the sqlite database, with the same query
+/// semantics as the handlers in `shared/DB.hs`.
+#[derive(Debug, Default)]
+pub struct Store {
+    entries: Vec<Entry>,
+    cache: Vec<Cache>,
+    tags: Vec<(i32, String)>,
+    completed: HashSet<i32>,
+}
+
+// xorshift, good enough for fake data and keeps the dependency list short
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+}
+
+const DOMAINS: &[&str] = &[
+    "github.com",
+    "export.arxiv.org",
+    "www.youtube.com",
+    "medium.com",
+    "www.reddit.com",
+    "twitter.com",
+    "news.ycombinator.com",
+    "en.wikipedia.org",
+    "blog.example.org",
+];
+
+const WORDS: &[&str] = &[
+    "attention", "transformer", "rust", "haskell", "memory", "graph", "neural",
+    "compiler", "types", "search", "index", "local-first", "sqlite", "notes",
+    "garden", "embedding", "retrieval", "latency", "tokenizer", "wasm",
+    "design", "systems", "learning", "distributed", "functional", "knowledge",
+];
+
+// ordered roughly by how often they get used
+const TAGS: &[&str] = &[
+    "ml", "rust", "haskell", "reading", "papers", "video", "todo", "tools",
+    "databases", "web", "news", "math", "design", "music",
+];
+
+fn title(rng: &mut Rng) -> String {
+    let count = 3 + rng.below(6);
+    let words: Vec<&str> = (0..count).map(|_| rng.pick(WORDS)).collect();
+    let mut title = words.join(" ");
+    if let Some(first) = title.get_mut(0..1) {
+        first.make_ascii_uppercase();
+    }
+    title
+}
+
+fn url(rng: &mut Rng, id: i32) -> String {
+    match rng.pick(DOMAINS) {
+        "www.youtube.com" => format!("https://www.youtube.com/watch?v=mock{:07}", id),
+        "export.arxiv.org" => format!("https://export.arxiv.org/abs/21{:02}.{:05}", 1 + id % 12, id),
+        domain => format!("https://{}/{}/{}", domain, rng.pick(WORDS), id),
+    }
+}
+
+fn to_timestamp(date: &str, time: &str) -> Option<Timestamp> {
+    let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    let tod = NaiveTime::parse_from_str(time, "%H:%M:%S").ok()?;
+    let utc = NaiveDateTime::new(day, tod).and_utc().timestamp();
+    Some(Timestamp {
+        day: (day.year(), day.month() as i32, day.day() as i32),
+        time_of_day: (tod.hour() as i32, tod.minute() as i32, tod.second() as i32),
+        utc,
+    })
+}
+
+impl Store {
+    /// `count` entries spread over the last three years, about a fifth of them
+    /// plain notes without a url.
+    pub fn generate(count: usize, seed: u64) -> Self {
+        let mut rng = Rng(seed.max(1));
+        let mut store = Store::default();
+        let now = Local::now().naive_local();
+        let span_minutes = 3 * 365 * 24 * 60;
+        let mut stamps: Vec<NaiveDateTime> = (0..count)
+            .map(|_| now - Duration::minutes(rng.below(span_minutes) as i64))
+            .collect();
+        stamps.sort();
+        for stamp in stamps {
+            let content = if rng.chance(20) {
+                format!("{} - {}", title(&mut rng), title(&mut rng).to_lowercase())
+            } else {
+                url(&mut rng, store.entries.len() as i32 + 1)
+            };
+            let tag_count = rng.below(3);
+            let tags: Vec<String> = (0..tag_count)
+                // squaring biases picks towards the front of TAGS
+                .map(|_| {
+                    let i = rng.below(TAGS.len());
+                    TAGS[i * i / TAGS.len()].to_string()
+                })
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect();
+            let id = store.insert(
+                &stamp.format("%Y-%m-%d").to_string(),
+                &stamp.format("%H:%M:%S").to_string(),
+                &content,
+                &tags,
+            );
+            if let Some(cached) = store.cache.last_mut() {
+                if cached.url.is_some() {
+                    cached.content = Some(title(&mut rng));
+                }
+            }
+            if rng.chance(25) {
+                store.completed.insert(id);
+            }
+        }
+        store
+    }
+
+    fn insert(&mut self, date: &str, time: &str, content: &str, tags: &[String]) -> i32 {
+        let entry_id = self.entries.len() as i32 + 1;
+        let is_url = content.starts_with("http://") || content.starts_with("https://");
+        self.entries.push(Entry {
+            entry_id,
+            date: date.to_string(),
+            time: time.to_string(),
+            content: content.to_string(),
+        });
+        self.cache.push(Cache {
+            time: time.to_string(),
+            entry_id,
+            content: Some(content.to_string()),
+            date: date.to_string(),
+            url: if is_url { Some(content.to_string()) } else { None },
+            thumbnail_file: None,
+        });
+        for tag in tags {
+            self.tags.push((entry_id, tag.clone()));
+        }
+        entry_id
+    }
+
+    fn entry_tags(&self, entry_id: i32) -> impl Iterator<Item = &str> {
+        self.tags
+            .iter()
+            .filter(move |(id, _)| *id == entry_id)
+            .map(|(_, tag)| tag.as_str())
+    }
+
+    /// `allTags` - tags used more than `min` times, sorted by name.
+    pub fn all_tags(&self, min: Option<usize>) -> Vec<String> {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for (_, tag) in &self.tags {
+            *counts.entry(tag.as_str()).or_default() += 1;
+        }
+        counts
+            .into_iter()
+            .filter(|(_, count)| min.is_none_or(|min| *count > min))
+            .map(|(tag, _)| tag.to_string())
+            .collect()
+    }
+
+    pub fn all_entries(&self) -> Vec<Entry> {
+        self.entries.clone()
+    }
+
+    pub fn all_timestamps(&self) -> Vec<Timestamp> {
+        self.entries
+            .iter()
+            .filter_map(|e| to_timestamp(&e.date, &e.time))
+            .collect()
+    }
+
+    /// `allCache` - entries matching any of the tags within the date window,
+    /// newest first unless `sortdir=fwd`.
+    pub fn all_cache(&self, params: &CacheParams) -> Vec<Cache> {
+        let mut rows: Vec<Cache> = self
+            .cache
+            .iter()
+            .filter(|c| {
+                params.tags.is_empty()
+                    || self.entry_tags(c.entry_id).any(|t| params.tags.iter().any(|p| p == t))
+            })
+            .filter(|c| params.start_date.as_ref().is_none_or(|d| &c.date >= d))
+            .filter(|c| params.end_date.as_ref().is_none_or(|d| &c.date <= d))
+            .filter(|c| !(params.hide_completed && self.completed.contains(&c.entry_id)))
+            .cloned()
+            .collect();
+        // newest first, also the tie order of url and title sorts
+        rows.sort_by(|a, b| (&b.date, &b.time).cmp(&(&a.date, &a.time)));
+        let key = match params.sort {
+            Some(SortBy::Url) => Some(SortKey::Domain),
+            Some(SortBy::Title) => Some(SortKey::Title),
+            Some(SortBy::Time) | None => None,
+        };
+        match key {
+            // same order as `cacheOrder`, which the frontend sorts by too
+            Some(key) => GallerySort {
+                key,
+                dir: params.sortdir.unwrap_or(SortDir::Fwd),
+            }
+            .apply(&mut rows),
+            None if params.sortdir == Some(SortDir::Fwd) => rows.reverse(),
+            None => {}
+        }
+        rows.into_iter()
+            .skip(params.offset)
+            .take(params.limit.unwrap_or(50))
+            .collect()
+    }
+
+    /// `getCache` - the cache row of one entry, empty when there is none.
+    pub fn get_cache(&self, entry_id: i32) -> Vec<Cache> {
+        self.cache.iter().filter(|c| c.entry_id == entry_id).cloned().collect()
+    }
+
+    /// `search` - case insensitive substring match on url, title and tags.
+    pub fn search(&self, query: &str) -> Vec<Cache> {
+        let query = query.to_lowercase();
+        let matches = |field: Option<&str>| field.is_some_and(|f| f.to_lowercase().contains(&query));
+        let mut rows: Vec<Cache> = self
+            .cache
+            .iter()
+            .filter(|c| {
+                matches(c.url.as_deref())
+                    || matches(c.content.as_deref())
+                    || self.entry_tags(c.entry_id).any(|t| matches(Some(t)))
+            })
+            .cloned()
+            .collect();
+        rows.sort_by(|a, b| (&b.date, &b.time).cmp(&(&a.date, &a.time)));
+        rows
+    }
+
+    /// `searchHits` - the field `search` matched for each result. There is
+    /// no page text or OCR here, so no snippets either.
+    pub fn search_hits(&self, query: &str) -> Vec<SearchHit> {
+        let lower = query.to_lowercase();
+        let matches = |field: Option<&str>| field.is_some_and(|f| f.to_lowercase().contains(&lower));
+        self.search(query)
+            .into_iter()
+            .map(|c| {
+                let source = if matches(c.url.as_deref()) {
+                    HitSource::Url
+                } else if matches(c.content.as_deref()) {
+                    HitSource::Title
+                } else {
+                    HitSource::Tag
+                };
+                SearchHit { entry_id: c.entry_id, source, snippet: None }
+            })
+            .collect()
+    }
+
+    /// `linkEntryTags` - one row per entry/tag pair, untagged entries get a
+    /// null tag.
+    pub fn link_entry_tags(&self, filter: &[String]) -> Vec<EntryTag> {
+        let mut rows = Vec::new();
+        for entry in &self.entries {
+            let tags: Vec<&str> = self.entry_tags(entry.entry_id).collect();
+            if tags.is_empty() && filter.is_empty() {
+                rows.push(EntryTag { entry_id: entry.entry_id, tag: None });
+            }
+            for tag in tags {
+                if filter.is_empty() || filter.iter().any(|f| f == tag) {
+                    rows.push(EntryTag { entry_id: entry.entry_id, tag: Some(tag.to_string()) });
+                }
+            }
+        }
+        rows
+    }
+
+    /// `postNote` - stores the note with the current time, returns its id.
+    pub fn add_note(&mut self, content: &str, tags: &[String]) -> i32 {
+        let now = Local::now();
+        self.insert(
+            &now.format("%Y-%m-%d").to_string(),
+            &now.format("%H:%M:%S").to_string(),
+            content,
+            tags,
+        )
+    }
+
+    /// `postTags` - returns how many of `tags` were new.
+    pub fn add_tags(&mut self, entry_id: i32, tags: &[String]) -> i64 {
+        let mut added = 0;
+        for tag in tags {
+            if !self.entry_tags(entry_id).any(|t| t == tag) {
+                self.tags.push((entry_id, tag.clone()));
+                added += 1;
+            }
+        }
+        added
+    }
+
+    pub fn remove_tags(&mut self, entry_id: i32, tags: &[String]) -> i64 {
+        let before = self.tags.len();
+        self.tags.retain(|(id, tag)| *id != entry_id || !tags.contains(tag));
+        (before - self.tags.len()) as i64
+    }
+
+    pub fn set_completed(&mut self, entry_id: i32, state: bool) {
+        if state {
+            self.completed.insert(entry_id);
+        } else {
+            self.completed.remove(&entry_id);
+        }
+    }
+
+    pub fn all_completed(&self) -> Vec<i32> {
+        let mut ids: Vec<i32> = self.completed.iter().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    pub fn is_completed(&self, entry_id: i32) -> bool {
+        self.completed.contains(&entry_id)
+    }
+}
@@ -0,0 +1,276 @@
+//! Mock OpenMemex server for frontend development. Serves the same routes as
+//! `CombinedAPI` in `server/Main.hs` from synthetic in-memory data, so the
+//! Yew frontend can be worked on without building the Haskell stack.
+//!
+//!     cargo run -p mockserver -- --port 3000 --entries 500
+//!
+//! then open http://localhost:3000/frontend/index.html
+
+mod data;
+
+use data::{CacheParams, Store};
+use frontend::api::{AddNotePayload, CompletedPayload, ServerConfig, SortBy, SortDir, TagsPayload};
+use serde::Serialize;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const USAGE: &str = "usage: mockserver [--port INT] [--entries INT] [--seed INT] [--tag_thresh INT] [--static DIR]";
+
+#[derive(Debug)]
+struct Options {
+    port: u16,
+    entries: usize,
+    seed: u64,
+    tag_thresh: i32,
+    static_dir: PathBuf,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            port: 3000,
+            entries: 300,
+            seed: 42,
+            tag_thresh: 1,
+            static_dir: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../frontend/static"),
+        }
+    }
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or(format!("missing value for {}", flag))?;
+        let invalid = |_| format!("invalid value for {}: {}", flag, value);
+        match flag.as_str() {
+            "--port" => options.port = value.parse().map_err(invalid)?,
+            "--entries" => options.entries = value.parse().map_err(invalid)?,
+            "--seed" => options.seed = value.parse().map_err(invalid)?,
+            "--tag_thresh" => options.tag_thresh = value.parse().map_err(invalid)?,
+            "--static" => options.static_dir = PathBuf::from(value),
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+    }
+    Ok(options)
+}
+
+type Reply = Response<std::io::Cursor<Vec<u8>>>;
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+// the frontend may be served from a different origin (miniserve, wasm-pack)
+fn with_cors(response: Reply) -> Reply {
+    response
+        .with_header(header("Access-Control-Allow-Origin", "*"))
+        .with_header(header("Access-Control-Allow-Headers", "Content-Type"))
+        .with_header(header("Access-Control-Allow-Methods", "GET, POST, OPTIONS"))
+}
+
+fn json<T: Serialize>(value: &T) -> Reply {
+    let body = serde_json::to_vec(value).expect("serializable response");
+    Response::from_data(body).with_header(header("Content-Type", "application/json"))
+}
+
+fn error(status: u16, message: &str) -> Reply {
+    Response::from_string(message).with_status_code(status)
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).unwrap_or("") {
+        "html" => "text/html; charset=utf-8",
+        "js" => "application/javascript",
+        "wasm" => "application/wasm",
+        "css" => "text/css",
+        "json" => "application/json",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "svg" => "image/svg+xml",
+        "glsl" | "txt" | "md" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// `/frontend/..` - files from the static directory. Unknown extensionless
+/// paths fall back to index.html so router urls survive a reload.
+fn serve_static(static_dir: &Path, rel: &str) -> Reply {
+    let rel = Path::new(rel.trim_start_matches('/'));
+    if rel.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return error(404, "not found");
+    }
+    let mut path = static_dir.join(rel);
+    if path.is_dir() {
+        path = path.join("index.html");
+    }
+    if !path.is_file() && rel.extension().is_none() {
+        path = static_dir.join("index.html");
+    }
+    match fs::read(&path) {
+        Ok(bytes) => Response::from_data(bytes).with_header(header("Content-Type", content_type(&path))),
+        Err(_) => error(404, "not found"),
+    }
+}
+
+fn query_pairs(query: &str) -> Vec<(String, String)> {
+    url::form_urlencoded::parse(query.as_bytes()).into_owned().collect()
+}
+
+fn cache_params(query: &str) -> Result<CacheParams, String> {
+    let mut params = CacheParams::default();
+    for (key, value) in query_pairs(query) {
+        match key.as_str() {
+            "sort" if value == "time" => params.sort = Some(SortBy::Time),
+            "sort" if value == "url" => params.sort = Some(SortBy::Url),
+            "sort" if value == "title" => params.sort = Some(SortBy::Title),
+            "sortdir" if value == "fwd" => params.sortdir = Some(SortDir::Fwd),
+            "sortdir" if value == "rev" => params.sortdir = Some(SortDir::Rev),
+            "tag" => params.tags.push(value),
+            "limit" => params.limit = Some(value.parse().map_err(|_| "Invalid limit")?),
+            "offset" => params.offset = value.parse().map_err(|_| "Invalid offset")?,
+            "hidecompleted" => params.hide_completed = value.parse().map_err(|_| "Invalid hidecompleted")?,
+            "startDate" => params.start_date = Some(value),
+            "endDate" => params.end_date = Some(value),
+            "sort" => return Err("Invalid sort specification".to_string()),
+            "sortdir" => return Err("Invalid sort direction".to_string()),
+            _ => {}
+        }
+    }
+    Ok(params)
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(request: &mut Request) -> Result<T, String> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .read_to_string(&mut body)
+        .map_err(|e| e.to_string())?;
+    serde_json::from_str(&body).map_err(|e| e.to_string())
+}
+
+fn route(store: &mut Store, options: &Options, request: &mut Request) -> Reply {
+    let full = request.url().to_string();
+    let (path, query) = match full.split_once('?') {
+        Some((path, query)) => (path, query),
+        None => (full.as_str(), ""),
+    };
+    let segments: Vec<String> = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| urlencoding::decode(s).map(|s| s.into_owned()).unwrap_or_else(|_| s.to_string()))
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    match (request.method(), segments.as_slice()) {
+        (Method::Options, _) => Response::from_data(Vec::new()),
+        (Method::Get, []) => json(&["n2s API"]),
+        (Method::Get, ["all", "tags"]) => {
+            let min = query_pairs(query)
+                .into_iter()
+                .find(|(k, _)| k == "min")
+                .and_then(|(_, v)| v.parse().ok());
+            json(&store.all_tags(min))
+        }
+        (Method::Get, ["all", "entries"]) => json(&store.all_entries()),
+        (Method::Get, ["all", "cache"]) => match cache_params(query) {
+            Ok(params) => json(&store.all_cache(&params)),
+            Err(message) => error(400, &message),
+        },
+        (Method::Get, ["all", "completed"]) => json(&store.all_completed()),
+        (Method::Get, ["all", "timestamps"]) => json(&store.all_timestamps()),
+        (Method::Post, ["submit", "note"]) => match read_json::<AddNotePayload>(request) {
+            Ok(note) => json(&store.add_note(&note.note_content, &note.tags)),
+            Err(message) => error(400, &message),
+        },
+        (Method::Post, ["submit", "tags"]) => match read_json::<TagsPayload>(request) {
+            Ok(tags) => json(&store.add_tags(tags.entry_id, &tags.tags)),
+            Err(message) => error(400, &message),
+        },
+        (Method::Post, ["submit", "tags", "remove"]) => match read_json::<TagsPayload>(request) {
+            Ok(tags) => json(&store.remove_tags(tags.entry_id, &tags.tags)),
+            Err(message) => error(400, &message),
+        },
+        (Method::Post, ["submit", "completed"]) => match read_json::<CompletedPayload>(request) {
+            Ok(completed) => {
+                store.set_completed(completed.entry_id, completed.state);
+                json(&0)
+            }
+            Err(message) => error(400, &message),
+        },
+        (Method::Get, ["get", "completed", id]) => match id.parse() {
+            Ok(id) => json(&[store.is_completed(id)]),
+            Err(_) => error(400, "Invalid entry_id"),
+        },
+        (Method::Get, ["get", "cache", id]) => match id.parse() {
+            Ok(id) => json(&store.get_cache(id)),
+            Err(_) => error(400, "Invalid entry_id"),
+        },
+        (Method::Get, ["search", q]) => json(&store.search(q)),
+        (Method::Get, ["search", "hits", q]) => json(&store.search_hits(q)),
+        (Method::Get, ["frontend", rest @ ..]) => serve_static(&options.static_dir, &rest.join("/")),
+        (Method::Get, ["link", "entry", "tags"]) => {
+            let filter: Vec<String> = query_pairs(query)
+                .into_iter()
+                .filter(|(k, _)| k == "filter")
+                .map(|(_, v)| v)
+                .collect();
+            json(&store.link_entry_tags(&filter))
+        }
+        // no models here: the doubled value of hasktorch, and byte values
+        // between roberta's <s> and </s> ids instead of real token ids
+        (Method::Get, ["test", "torch", value]) => match value.parse::<f32>() {
+            Ok(value) => json(&[serde_json::json!({
+                "msg": "hello. f(x) = 2 * x from hasktorch.",
+                "value": [2.0 * value],
+            })]),
+            Err(_) => error(400, "Invalid value"),
+        },
+        (Method::Get, ["test", "huggingface", value]) => {
+            let ids: Vec<u32> = std::iter::once(0)
+                .chain(value.bytes().map(u32::from))
+                .chain(std::iter::once(2))
+                .collect();
+            json(&[serde_json::json!({ "hfToken": value, "hfResult": ids })])
+        }
+        (Method::Get, ["config"]) => json(&[ServerConfig {
+            tag_threshold: options.tag_thresh,
+            port: options.port as i32,
+            db_filename: "mock".to_string(),
+        }]),
+        _ => error(404, "not found"),
+    }
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            std::process::exit(1);
+        }
+    };
+    let mut store = Store::generate(options.entries, options.seed);
+    let server = match Server::http(("0.0.0.0", options.port)) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("could not bind port {}: {}", options.port, e);
+            std::process::exit(1);
+        }
+    };
+    eprintln!(
+        "OpenMemex mock server running on port {} ({} entries, seed {}, static files from {})",
+        options.port,
+        options.entries,
+        options.seed,
+        options.static_dir.display()
+    );
+    for mut request in server.incoming_requests() {
+        let response = with_cors(route(&mut store, &options, &mut request));
+        println!("{} {} {}", request.method(), request.url(), response.status_code().0);
+        if let Err(e) = request.respond(response) {
+            eprintln!("failed to respond: {}", e);
+        }
+    }
+}
@@ -40,6 +40,13 @@ postNoteH note = liftIO $ postNote note
 -- | Alter state for content being completed
 postCompletedH entryID = liftIO $ postCompleted entryID
 
+-- | Add tags to an existing entry
+postTagsH :: PostTags -> Handler Int64
+postTagsH tags = liftIO $ postTags tags
+
+postRemoveTagsH :: PostTags -> Handler Int64
+postRemoveTagsH tags = liftIO $ postRemoveTags tags
+
 -- | Retrieve a list of all topic tags
 allTagsH :: Maybe Int -> Handler [String]
 allTagsH minCount = liftIO $ allTags minCount
@@ -53,6 +60,7 @@ allCacheH
   -> Maybe SortDir 
   -> [Text] -- ^ filterTags
   -> Maybe Int -- ^ limit
+  -> Maybe Int -- ^ offset
   -> Maybe Bool 
   -> Maybe Day 
   -> Maybe Day 
@@ -62,17 +70,30 @@ allCacheH
   sortdir 
   filterTags 
   limit 
+  offset
   hideCompleted 
   startDate
   endDate
-  = liftIO (allCache sortby sortdir filterTags limit hideCompleted startDate endDate)
+  = liftIO (allCache sortby sortdir filterTags limit offset hideCompleted startDate endDate)
     
 -- | Retrieve state for content being completed
 getCompletedH entryID = liftIO $ getCompleted entryID
 
+-- | Ids of all completed entries (for gallery badges)
+allCompletedH :: Handler [Int]
+allCompletedH = liftIO allCompleted
+
+-- | Retrieve a single cache entry (for detail deep links)
+getCacheH :: Int -> Handler [CacheView]
+getCacheH entryID = liftIO $ getCache entryID
+
 -- | Searchbox retrieval
 searchH query = liftIO $ search query
 
+-- | Which field each search result matched (for the gallery hit indicator)
+searchHitsH :: String -> Handler [SearchHit]
+searchHitsH query = liftIO $ searchHits query
+
 {- Implementations (any DB queries are in DB.hs) -}
 
 -- | Add a note
@@ -104,3 +125,18 @@ postCompleted (PostCompleted entryID state) = do
     False -> removeCompleted entryID
   
   pure 0
+
+-- | Add tags to an entry, returns how many were new
+postTags :: PostTags -> IO Int64
+postTags (PostTags entryID tags) = do
+  putStrLn $ "Tagging " ++ show entryID ++ " with " ++ show tags
+  existing <- entryTags entryID
+  let new = filter (`notElem` existing) tags
+  mapM_ (addTag (fromIntegral entryID)) new
+  pure . fromIntegral $ length new
+
+-- | Remove tags from an entry, returns how many rows were deleted
+postRemoveTags :: PostTags -> IO Int64
+postRemoveTags (PostTags entryID tags) = do
+  putStrLn $ "Untagging " ++ show entryID ++ " from " ++ show tags
+  sum <$> mapM (removeTag entryID) tags
@@ -51,6 +51,7 @@ type AllCacheAPI =
     :> QueryParam "sortdir" SortDir
     :> QueryParams "tag" Text
     :> QueryParam "limit" Int
+    :> QueryParam "offset" Int
     :> QueryParam "hidecompleted" Bool
     :> QueryParam "startDate" Day
     :> QueryParam "endDate" Day
@@ -61,11 +62,21 @@ type ContentAPI = "content" :> Capture "query" String :> Get '[JSON] [CacheView]
 type EntryAPI = "submit" :> "note" :> ReqBody '[JSON] PostNote :> Post '[JSON] Int64
 
 type CompletedAPI = "submit" :> "completed" :> ReqBody '[JSON] PostCompleted :> Post '[JSON] Int64
+
+type TagsAPI = "submit" :> "tags" :> ReqBody '[JSON] PostTags :> Post '[JSON] Int64
+
+type RemoveTagsAPI = "submit" :> "tags" :> "remove" :> ReqBody '[JSON] PostTags :> Post '[JSON] Int64
   
 type GetCompletedAPI = "get" :> "completed" :> Capture "entry_id" Int :> Get '[JSON] [Bool]
+
+type AllCompletedAPI = "all" :> "completed" :> Get '[JSON] [Int]
+
+type GetCacheAPI = "get" :> "cache" :> Capture "entry_id" Int :> Get '[JSON] [CacheView]
   
 type SearchAPI = "search" :> Capture "query" String :> Get '[JSON] [CacheView]
 
+type SearchHitsAPI = "search" :> "hits" :> Capture "query" String :> Get '[JSON] [SearchHit]
+
 type FrontendAPI = "frontend" :> Raw
 
 type LinkEntryTagsAPI =
@@ -99,8 +110,13 @@ type CombinedAPI =
     :<|> AllTimestampsAPI
     :<|> EntryAPI 
     :<|> CompletedAPI 
+    :<|> TagsAPI
+    :<|> RemoveTagsAPI
     :<|> GetCompletedAPI 
+    :<|> GetCacheAPI
+    :<|> AllCompletedAPI
     :<|> SearchAPI
+    :<|> SearchHitsAPI
     :<|> FrontendAPI
     :<|> LinkEntryTagsAPI
     :<|> HelloTorchAPI
@@ -119,8 +135,13 @@ server config =
     :<|> allTimestampsH
     :<|> postNoteH
     :<|> postCompletedH
+    :<|> postTagsH
+    :<|> postRemoveTagsH
     :<|> getCompletedH
+    :<|> getCacheH
+    :<|> allCompletedH
     :<|> searchH
+    :<|> searchHitsH
     :<|> frontendH
     :<|> linkEntryTagsH
     :<|> helloTorchH
@@ -133,6 +154,7 @@ instance FromHttpApiData SortBy where
   parseUrlPiece value = case value of
     "time" -> Right SortTime
     "url" -> Right SortUrl
+    "title" -> Right SortTitle
     _ -> Left "Invalid sort specification"
 
 instance FromHttpApiData SortDir where
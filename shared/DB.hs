@@ -106,7 +106,7 @@ data WebPage = WebPage
   }
   deriving (Eq, Show, Generic)
 
-data SortBy = SortTime | SortUrl deriving (Show, Generic)
+data SortBy = SortTime | SortUrl | SortTitle deriving (Show, Generic)
 
 data SortDir = SortFwd | SortRev deriving (Show, Generic)
 
@@ -132,6 +132,19 @@ instance FromRow CacheView where
 
 instance ToJSON CacheView
 
+-- | Search result annotation, see searchHits
+data SearchHit = SearchHit
+  { shEntryID :: Int,
+    shSource :: String, -- url, title, tag, body or ocr
+    shSnippet :: Maybe String
+  }
+  deriving (Show, Generic)
+
+instance FromRow SearchHit where
+  fromRow = SearchHit <$> field <*> field <*> field
+
+instance ToJSON SearchHit
+
 --  database representation
 data CacheEntry = CacheEntry
   { cacheForeignID :: Int, -- entryID
@@ -172,6 +185,10 @@ data PostCompleted = PostCompleted { pcEntryID :: Int, pcState :: Bool} deriving
 instance ToJSON PostCompleted
 instance FromJSON PostCompleted
 
+data PostTags = PostTags { ptEntryID :: Int, ptTags :: [String] } deriving (Show, Generic)
+instance ToJSON PostTags
+instance FromJSON PostTags
+
 dbFile = "openmemex.db"
 
 -- Helper functions
@@ -230,64 +247,100 @@ getEntry entryID = do
 
 -- handlers
 
+getCache :: Int -> IO [CacheView]
+getCache entryID = do
+  conn <- open dbFile
+  r <- queryNamed conn
+         "SELECT entry_id, cache_url, cache_content_type, cache_title, date, time, cache_screenshot_file, cache_thumbnail_file FROM cache WHERE entry_id = :entryID"
+         [":entryID" := entryID] :: IO [CacheView]
+  close conn
+  pure r -- empty if the entry has no cache row yet
+
 allCache 
   :: Maybe SortBy 
   -> Maybe SortDir 
   -> [Text] 
   -> Maybe Int 
+  -> Maybe Int 
   -> Maybe Bool 
   -> Maybe Day 
   -> Maybe Day 
   -> IO [CacheView]
-allCache sortby sortdir filterTags limit hideCompleted startDay endDay = do
-  -- TODO: support hideCompleted
+allCache sortby sortdir filterTags limit offset hideCompleted startDay endDay = do
   conn <- open dbFile
+  -- one row per entry however many of the tags it has, the tags are user
+  -- input and bound as parameters
   let tagCond = case filterTags of
                 [] -> []
-                _ -> [SqlCond ("tag IN ('" ++ (intercalate "','" $ unpack <$> filterTags) ++ "')")]
+                _ -> [SqlCond ("EXISTS (SELECT 1 FROM tags WHERE tags.entry_id=cache.entry_id AND tag IN " ++ placeholders ++ ")")]
   let dateStartCond = case startDay of 
                       Nothing -> []
                       Just t -> let (y, m, d) = toGregorian t in [SqlCond $ printf "date >= \"%.4d-%.2d-%.2d\"" y m d]
   let dateEndCond = case endDay of 
                       Nothing -> []
                       Just t -> let (y, m, d) = toGregorian t in [SqlCond $ printf "date <= \"%.4d-%.2d-%.2d\"" y m d]
-  let conditions = tagCond ++ dateStartCond  ++ dateEndCond
+  let completedCond = case hideCompleted of
+                      Just True -> [SqlCond "cache.entry_id NOT IN (SELECT entry_id FROM completed)"]
+                      _ -> []
+  let conditions = tagCond ++ dateStartCond  ++ dateEndCond ++ completedCond
   let query =
         defaultQuery
           { sqlSelect = SqlCol <$> ["cache.entry_id", "cache_url", "cache_content_type", "cache_title", "date", "time", "cache_screenshot_file", "cache_thumbnail_file"],
-            sqlFrom = if null filterTags
-                      then SqlFrom "cache"
-                      else SqlFrom $ "tags LEFT JOIN cache ON cache.entry_id=tags.entry_id",
+            sqlFrom = SqlFrom "cache",
             sqlLimit = Just limit',
+            sqlOffset = offset,
             sqlWhere = conditions,
-            sqlOrder = case sortdir of
-                        Just SortRev -> [SqlOrder "date DESC, time DESC"] -- TODO represent individual termws instead of using a string blob
-                        Just SortFwd -> [SqlOrder "date, time"]
-                        Nothing -> [SqlOrder "date DESC, time DESC"] -- TODO represent individual termws instead of using a string blob
+            sqlOrder = [SqlOrder $ cacheOrder sortby sortdir] -- TODO represent individual termws instead of using a string blob
           }
   let queryString = sql2string query
   print query
   putStrLn $ "\n" ++ queryString ++ "\n"
-  r <- query_ conn (Query . pack $ queryString)
+  r <- query conn (Query . pack $ queryString) filterTags
   close conn
   pure r
   where
     limit' = case limit of
       Nothing -> 50
       Just l -> l
+    placeholders = "(" ++ intercalate ", " (replicate (length filterTags) "?") ++ ")"
+
+-- | ORDER BY of allCache. Url and title order put entries without one last
+-- in either direction, ties newest first. The frontend re-sorts the loaded
+-- pages with the same keys (`sort_text` in frontend/src/sort.rs), so pages
+-- appended while scrolling stay in place.
+cacheOrder :: Maybe SortBy -> Maybe SortDir -> String
+cacheOrder sortby sortdir = case sortby of
+  Just SortUrl -> keyed hostExpr
+  Just SortTitle -> keyed "LOWER(TRIM(cache_title))"
+  _ -> case sortdir of
+    Just SortFwd -> "date, time"
+    _ -> "date DESC, time DESC"
+  where
+    dir = case sortdir of
+      Just SortRev -> " DESC"
+      _ -> " ASC"
+    keyed key = "(" ++ key ++ ") IS NULL, " ++ key ++ dir ++ ", date DESC, time DESC"
+    -- host of cache_url, lowercased and without a leading "www."
+    rest = "substr(cache_url, instr(cache_url, '://') + 3)"
+    host = "substr(" ++ rest ++ ", 1, instr(" ++ rest ++ " || '/', '/') - 1)"
+    hostExpr =
+      "CASE WHEN instr(cache_url, '://') = 0 THEN NULL"
+        ++ " WHEN " ++ host ++ " LIKE 'www.%' THEN LOWER(substr(" ++ host ++ ", 5))"
+        ++ " ELSE LOWER(" ++ host ++ ") END"
 
 linkEntryTags :: [String] -> IO [EntryTag]
 linkEntryTags filterTags = do
   conn <- open dbFile
-  let query =
+  let queryString =
         if filterTags == []
           then -- TODO - why does this return a runtime error for the empty case
           -- ConversionFailed {errSQLType = "NULL", errHaskellType = "[Char]", errMessage = "expecting SQLText column type"}
             "SELECT entries.entry_id, tag FROM entries LEFT JOIN tags on entries.entry_id=tags.entry_id"
-          else "SELECT entries.entry_id, tag FROM entries LEFT JOIN tags on entries.entry_id=tags.entry_id WHERE tag IN " ++ filterList
-  query_ conn (Query . pack $ query)
+          else "SELECT entries.entry_id, tag FROM entries LEFT JOIN tags on entries.entry_id=tags.entry_id WHERE tag IN " ++ placeholders
+  -- the tags are user input, bound as parameters rather than spliced in
+  query conn (Query . pack $ queryString) filterTags
   where
-    filterList = "(" ++ intercalate "," filterTags ++ ")"
+    placeholders = "(" ++ intercalate ", " (replicate (length filterTags) "?") ++ ")"
 
 crawlerOutput2cache :: [(Entry, String, Maybe WebPage)] -> [CacheEntry]
 crawlerOutput2cache out =
@@ -545,6 +598,24 @@ addTag entryID tag = do
   close conn
   pure r
 
+removeTag :: Int -> String -> IO Int64
+removeTag entryID tag = do
+  conn <- open dbFile
+  executeNamed
+    conn
+    "DELETE FROM tags WHERE entry_id = :entryID AND tag = :tag"
+    [":entryID" := entryID, ":tag" := tag]
+  r <- changes conn
+  close conn
+  pure $ fromIntegral r
+
+entryTags :: Int -> IO [String]
+entryTags entryID = do
+  conn <- open dbFile
+  r <- queryNamed conn "SELECT tag FROM tags WHERE entry_id = :entryID" [":entryID" := entryID] :: IO [Only String]
+  close conn
+  pure $ fromOnly <$> r
+
 addCompleted :: Int -> IO Int64
 addCompleted entryID = do
   (dt, tm) <- getDateTime
@@ -567,6 +638,13 @@ removeCompleted entryID = do
   close conn
   pure 0
 
+allCompleted :: IO [Int]
+allCompleted = do
+  conn <- open dbFile
+  r <- query_ conn "SELECT DISTINCT entry_id FROM completed" :: IO [Only Int]
+  close conn
+  pure $ fromOnly <$> r
+
 checkCompleted :: Int -> IO Bool
 checkCompleted entryID = do
   conn <- open dbFile
@@ -574,18 +652,55 @@ checkCompleted entryID = do
   close conn
   pure $ if null r then False else (not $ null (r !! 0))
 
+-- | Entries whose url, title, tags, cached body or OCR text contain :pattern
+searchCond :: String
+searchCond =
+  "cache_url LIKE :pattern OR cache_title LIKE :pattern OR cache_body LIKE :pattern " ++
+  "OR EXISTS (SELECT 1 FROM tags WHERE tags.entry_id = cache.entry_id AND tags.tag LIKE :pattern) " ++
+  "OR EXISTS (SELECT 1 FROM ocr WHERE ocr.entry_id = cache.entry_id AND ocr_content LIKE :pattern)"
+
+-- the ocr table is only written by writeOCR, searches shouldn't fail before that
+ensureOCRTable :: Connection -> IO ()
+ensureOCRTable conn =
+  execute_ conn "CREATE TABLE IF NOT EXISTS ocr (ocr_entry_id INTEGER PRIMARY KEY AUTOINCREMENT, entry_id INTEGER, ocr_file TEXT, ocr_content TEXT);"
+
 search :: String -> IO [CacheView]
 search query = do
   putStrLn $ "Searching for " ++ query
   conn <- open dbFile
+  ensureOCRTable conn
   let queryString = Query $ pack (
-                    "SELECT DISTINCT cache.entry_id, cache_url, cache_content_type, cache_title, date, time, cache_screenshot_file, cache_thumbnail_file " ++
+                    "SELECT cache.entry_id, cache_url, cache_content_type, cache_title, date, time, cache_screenshot_file, cache_thumbnail_file " ++
                     "FROM cache " ++
-                    "LEFT JOIN tags ON cache.entry_id=tags.entry_id " ++ 
-                    "WHERE cache_url LIKE '%" ++ query ++ "%' OR cache_title LIKE '%" ++ query ++ "%' OR tags.tag LIKE '%" ++ query ++ "%' " ++
+                    "WHERE " ++ searchCond ++ " " ++
                     "ORDER BY coalesce(datetime(\"date\"), datetime(\"time\")) DESC")
   print queryString
-  query_ conn queryString :: IO [CacheView]
+  r <- queryNamed conn queryString [":pattern" := ("%" ++ query ++ "%")] :: IO [CacheView]
+  close conn
+  pure r
+
+-- | Which field `search` matched for each result, with the text around the
+-- match for body and OCR hits
+searchHits :: String -> IO [SearchHit]
+searchHits query = do
+  conn <- open dbFile
+  ensureOCRTable conn
+  let tagMatch = "EXISTS (SELECT 1 FROM tags WHERE tags.entry_id = cache.entry_id AND tags.tag LIKE :pattern)"
+      snippet column = "substr(" ++ column ++ ", max(1, instr(lower(" ++ column ++ "), lower(:query)) - 60), 160)"
+      queryString = Query $ pack (
+                    "SELECT entry_id, " ++
+                    "CASE WHEN cache_url LIKE :pattern THEN 'url' " ++
+                    "WHEN cache_title LIKE :pattern THEN 'title' " ++
+                    "WHEN " ++ tagMatch ++ " THEN 'tag' " ++
+                    "WHEN cache_body LIKE :pattern THEN 'body' ELSE 'ocr' END, " ++
+                    "CASE WHEN cache_url LIKE :pattern OR cache_title LIKE :pattern OR " ++ tagMatch ++ " THEN NULL " ++
+                    "WHEN cache_body LIKE :pattern THEN " ++ snippet "cache_body" ++ " " ++
+                    "ELSE (SELECT " ++ snippet "ocr_content" ++ " FROM ocr WHERE ocr.entry_id = cache.entry_id AND ocr_content LIKE :pattern LIMIT 1) END " ++
+                    "FROM cache " ++
+                    "WHERE " ++ searchCond)
+  r <- queryNamed conn queryString [":pattern" := ("%" ++ query ++ "%"), ":query" := query] :: IO [SearchHit]
+  close conn
+  pure r
 
 wipeTesting :: IO ()
 wipeTesting = do
@@ -29,7 +29,8 @@ data SqlQuery = SqlQuery
     sqlFrom :: SqlFrom,
     sqlWhere :: [SqlCond],
     sqlOrder :: [SqlOrder],
-    sqlLimit :: Maybe Int
+    sqlLimit :: Maybe Int,
+    sqlOffset :: Maybe Int
   } deriving (Show)
 
 defaultQuery = SqlQuery {
@@ -38,7 +39,8 @@ defaultQuery = SqlQuery {
   sqlFrom = SqlFrom "",
   sqlWhere = [],
   sqlOrder = [],
-  sqlLimit = Nothing
+  sqlLimit = Nothing,
+  sqlOffset = Nothing
 }
 
 sql2string :: SqlQuery -> String
@@ -48,6 +50,7 @@ sql2string SqlQuery {..} =
     ++ whereClause
     ++ orderClause
     ++ limitClause
+    ++ offsetClause
   where
     distinctClause = if sqlDistinct then " DISTINCT" else ""
     fromClause = " FROM " ++ (sqlFromTable sqlFrom)
@@ -62,6 +65,9 @@ sql2string SqlQuery {..} =
     limitClause = case sqlLimit of
       Nothing -> ""
       Just n -> " LIMIT " ++ show n
+    offsetClause = case sqlOffset of
+      Nothing -> ""
+      Just n -> " OFFSET " ++ show n
 
 bracketQuery :: FromRow r => String -> ReaderT Sqlite IO [r]
 bracketQuery queryString = do